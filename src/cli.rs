@@ -0,0 +1,113 @@
+//! Headless mode, used instead of the window when arguments are given.
+
+use crate::format_event;
+use anyhow::{Result, bail};
+use std::path::PathBuf;
+use std::sync::atomic::AtomicBool;
+use webp_converter_app::{ConvertOptions, LogEvent, Preset, watch_dir_threads};
+
+pub const USAGE: &str = "Usage: egui_app --watch <input dir> <output dir> [--preset <file.toml>]";
+
+/// What the command line asked for.
+#[derive(Debug, PartialEq)]
+pub struct Cli {
+    pub watch: Option<(String, String)>,
+    /// A preset file exported from the GUI, applied over the defaults.
+    pub preset: Option<PathBuf>,
+}
+
+impl Cli {
+    /// Parses the arguments that follow the program name.
+    pub fn parse(args: &[String]) -> Result<Self> {
+        let mut cli = Self {
+            watch: None,
+            preset: None,
+        };
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--watch" => {
+                    let (Some(input), Some(output)) = (args.next(), args.next()) else {
+                        bail!("`--watch` needs an input and an output directory");
+                    };
+                    cli.watch = Some((input.clone(), output.clone()));
+                }
+                "--preset" => {
+                    let Some(path) = args.next() else {
+                        bail!("`--preset` needs a file");
+                    };
+                    cli.preset = Some(PathBuf::from(path));
+                }
+                other => bail!("Unknown argument `{other}`"),
+            }
+        }
+        if cli.watch.is_none() {
+            bail!("Nothing to do");
+        }
+        Ok(cli)
+    }
+}
+
+/// Runs the command line in `args` and returns the process exit code.
+pub fn run(args: &[String]) -> i32 {
+    attach_console();
+    if args.iter().any(|a| a == "-h" || a == "--help") {
+        println!("{USAGE}");
+        return 0;
+    }
+    let cli = match Cli::parse(args) {
+        Ok(cli) => cli,
+        Err(e) => {
+            eprintln!("{e}\n{USAGE}");
+            return 2;
+        }
+    };
+    let mut options = ConvertOptions::default();
+    if let Some(path) = &cli.preset {
+        match Preset::load(path) {
+            Ok(preset) => preset.apply(&mut options),
+            Err(e) => {
+                eprintln!("Error: {e:#}");
+                return 1;
+            }
+        }
+    }
+    let result = match &cli.watch {
+        // Watches until the process is interrupted.
+        Some((input, output)) => {
+            let stop = AtomicBool::new(false);
+            watch_dir_threads(input, output, &options, &stop, print_event)
+        }
+        None => Ok(()),
+    };
+    match result {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("Error: {e:#}");
+            1
+        }
+    }
+}
+
+fn print_event(event: LogEvent) {
+    let (line, _) = format_event(&event);
+    match event {
+        LogEvent::Error { .. } => eprintln!("{line}"),
+        _ => println!("{line}"),
+    }
+}
+
+/// On Windows the app is a GUI-subsystem program, which starts without a
+/// console; output goes to the console it was launched from instead.
+#[cfg(windows)]
+fn attach_console() {
+    use winapi::um::wincon::{ATTACH_PARENT_PROCESS, AttachConsole};
+    // SAFETY: takes no pointers. It fails harmlessly when there is no
+    // parent console, e.g. when started from Explorer.
+    unsafe {
+        AttachConsole(ATTACH_PARENT_PROCESS);
+    }
+}
+
+#[cfg(not(windows))]
+fn attach_console() {}
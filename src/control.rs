@@ -0,0 +1,70 @@
+use crate::LogEvent;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+
+/// Shared handle for pausing a running batch from another thread.
+///
+/// Clones control the same run. Pausing stops new files from being
+/// dispatched; files already being converted finish normally.
+#[derive(Debug, Clone, Default)]
+pub struct RunControl {
+    inner: Arc<Inner>,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    state: Mutex<State>,
+    changed: Condvar,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    paused: bool,
+    /// A worker has logged `Paused` and nobody has logged `Resumed` yet.
+    announced: bool,
+}
+
+impl RunControl {
+    pub fn pause(&self) {
+        self.state().paused = true;
+    }
+
+    pub fn resume(&self) {
+        self.state().paused = false;
+        self.inner.changed.notify_all();
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.state().paused
+    }
+
+    /// Called by workers before starting a file: blocks while paused,
+    /// logging `Paused` and `Resumed` once per pause across all workers.
+    pub(crate) fn checkpoint<F>(&self, log_fn: &F)
+    where
+        F: Fn(LogEvent),
+    {
+        let mut state = self.state();
+        if !state.paused {
+            return;
+        }
+        if !state.announced {
+            state.announced = true;
+            log_fn(LogEvent::Paused);
+        }
+        while state.paused {
+            state = self
+                .inner
+                .changed
+                .wait(state)
+                .unwrap_or_else(|e| e.into_inner());
+        }
+        if state.announced {
+            state.announced = false;
+            log_fn(LogEvent::Resumed);
+        }
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.inner.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
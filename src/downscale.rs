@@ -0,0 +1,135 @@
+//! Shrinking sources that exceed `ConvertOptions::max_pixels`.
+
+use crate::RgbaImage;
+use crate::filter::Header;
+use anyhow::{Context, Result, bail};
+use jpeg_decoder::{CodingProcess, Decoder, PixelFormat};
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+/// Decodes a JPEG at reduced scale and box-filters the rest of the way
+/// within `max_pixels`.
+///
+/// The scaled IDCT decodes straight to 1/2, 1/4 or 1/8 size, so a baseline
+/// JPEG never exists at full size in memory. Progressive JPEGs still hold
+/// every coefficient until their last scan; see [`peak_bytes`].
+pub(crate) fn load_jpeg_within(path: &Path, max_pixels: u64) -> Result<RgbaImage> {
+    let file = File::open(path).with_context(|| format!("Failed to read {:?}", path))?;
+    let mut decoder = Decoder::new(BufReader::new(file));
+    decoder
+        .read_info()
+        .with_context(|| format!("Invalid JPEG: {:?}", path))?;
+    let Some(info) = decoder.info() else {
+        bail!("Invalid JPEG: {:?}", path);
+    };
+    let (width, height) = (u32::from(info.width), u32::from(info.height));
+    let factor = scale_factor(width, height, max_pixels);
+    // Both targets are at most the source size, which is itself a u16.
+    let (scaled_w, scaled_h) =
+        decoder.scale(width.div_ceil(factor) as u16, height.div_ceil(factor) as u16)?;
+    let pixels = decoder
+        .decode()
+        .with_context(|| format!("Failed to decode {:?}", path))?;
+    let rgb = to_rgb(pixels, info.pixel_format)?;
+    let (scaled_w, scaled_h) = (u32::from(scaled_w), u32::from(scaled_h));
+    let factor = scale_factor(scaled_w, scaled_h, max_pixels);
+    Ok(box_filter(&rgb, scaled_w, scaled_h, factor))
+}
+
+/// Reads a JPEG's size and coding process without decoding any scans.
+pub(crate) fn jpeg_header(path: &Path) -> Option<Header> {
+    let mut decoder = Decoder::new(BufReader::new(File::open(path).ok()?));
+    decoder.read_info().ok()?;
+    let info = decoder.info()?;
+    Some(Header {
+        width: u32::from(info.width),
+        height: u32::from(info.height),
+        progressive: info.coding_process == CodingProcess::DctProgressive,
+    })
+}
+
+/// Roughly the most memory [`load_jpeg_within`] holds at once for `header`:
+/// the scaled RGB decode plus its RGBA result, and for progressive JPEGs
+/// two bytes per sample of full-size coefficients.
+pub(crate) fn peak_bytes(header: Header, max_pixels: u64) -> u64 {
+    let Header {
+        width,
+        height,
+        progressive,
+    } = header;
+    let factor = scale_factor(width, height, max_pixels);
+    let (target_w, target_h) = (width.div_ceil(factor), height.div_ceil(factor));
+    // Mirrors the decoder's choice: the smallest scale (in eighths) that
+    // still reaches the target along either axis.
+    let scaled = |len: u32, eighths: u32| (len * eighths).div_ceil(8);
+    let eighths = [1, 2, 4]
+        .into_iter()
+        .find(|&e| scaled(width, e) >= target_w || scaled(height, e) >= target_h)
+        .unwrap_or(8);
+    let decoded = u64::from(scaled(width, eighths)) * u64::from(scaled(height, eighths));
+    let pixels = u64::from(width) * u64::from(height);
+    let coefficients = if progressive { pixels * 3 * 2 } else { 0 };
+    decoded * 3 + decoded.min(max_pixels) * 4 + coefficients
+}
+
+/// Converts the decoder's output to packed RGB.
+fn to_rgb(pixels: Vec<u8>, format: PixelFormat) -> Result<Vec<u8>> {
+    Ok(match format {
+        PixelFormat::RGB24 => pixels,
+        PixelFormat::L8 => pixels.iter().flat_map(|&l| [l, l, l]).collect(),
+        // The decoder has already undone Adobe's inversion, so 0 means no ink.
+        PixelFormat::CMYK32 => pixels
+            .chunks_exact(4)
+            .flat_map(|px| {
+                let ink = |v: u8| ((255 - u32::from(v)) * (255 - u32::from(px[3])) / 255) as u8;
+                [ink(px[0]), ink(px[1]), ink(px[2])]
+            })
+            .collect(),
+        PixelFormat::L16 => bail!("16-bit JPEGs cannot be downscaled"),
+    })
+}
+
+/// Smallest whole factor that shrinks `width`×`height` to `max_pixels` or fewer.
+fn scale_factor(width: u32, height: u32, max_pixels: u64) -> u32 {
+    let pixels = u64::from(width) * u64::from(height);
+    let mut factor = ((pixels as f64 / max_pixels.max(1) as f64).sqrt().ceil() as u32).max(1);
+    while u64::from(width.div_ceil(factor)) * u64::from(height.div_ceil(factor)) > max_pixels
+        && factor < width.max(height)
+    {
+        factor += 1;
+    }
+    factor
+}
+
+/// Averages each `factor`×`factor` block of an RGB image into one opaque
+/// RGBA pixel; blocks on the right and bottom edges may be partial.
+fn box_filter(rgb: &[u8], width: u32, height: u32, factor: u32) -> RgbaImage {
+    let (out_w, out_h) = (width.div_ceil(factor), height.div_ceil(factor));
+    let mut pixels = Vec::with_capacity(out_w as usize * out_h as usize * 4);
+    let mut sums = vec![[0u64; 4]; out_w as usize];
+    let stride = width as usize * 3;
+    for oy in 0..out_h {
+        sums.fill([0; 4]);
+        for y in oy * factor..((oy + 1) * factor).min(height) {
+            let start = y as usize * stride;
+            let row = &rgb[start..start + stride];
+            for (x, px) in row.chunks_exact(3).enumerate() {
+                let sum = &mut sums[x / factor as usize];
+                sum[0] += u64::from(px[0]);
+                sum[1] += u64::from(px[1]);
+                sum[2] += u64::from(px[2]);
+                sum[3] += 1;
+            }
+        }
+        for [r, g, b, n] in &sums {
+            let avg = |v: u64| ((v + n / 2) / n) as u8;
+            pixels.extend_from_slice(&[avg(*r), avg(*g), avg(*b), 255]);
+        }
+    }
+    RgbaImage {
+        width: out_w,
+        height: out_h,
+        pixels,
+    }
+}
@@ -0,0 +1,131 @@
+//! Just enough EXIF parsing to find when a JPEG was taken.
+
+use std::fs::File;
+use std::io::{self, BufReader, Read};
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+const TAG_EXIF_IFD: u16 = 0x8769;
+const TAG_DATE_TIME_ORIGINAL: u16 = 0x9003;
+const TYPE_ASCII: u16 = 2;
+
+/// Reads EXIF `DateTimeOriginal` from a JPEG. The tag carries no time zone,
+/// so it is interpreted as UTC. Other formats are not opened, and a JPEG is
+/// read no further than the start of its image data.
+pub(crate) fn date_time_original(path: &Path) -> Option<SystemTime> {
+    if !crate::is_jpeg(path) {
+        return None;
+    }
+    let file = File::open(path).ok()?;
+    date_time_original_from_tiff(&exif_segment(BufReader::new(file))?)
+}
+
+fn date_time_original_from_tiff(tiff: &[u8]) -> Option<SystemTime> {
+    let tiff = Tiff::new(tiff)?;
+    let exif_ifd = tiff.find_entry(tiff.u32(4)?, TAG_EXIF_IFD)?;
+    let entry = tiff.find_entry(tiff.u32(exif_ifd + 8)?, TAG_DATE_TIME_ORIGINAL)?;
+    if tiff.u16(entry + 2)? != TYPE_ASCII || tiff.u32(entry + 4)? < 19 {
+        return None;
+    }
+    let offset = tiff.u32(entry + 8)? as usize;
+    let text = std::str::from_utf8(tiff.data.get(offset..offset + 19)?).ok()?;
+    parse_exif_date(text)
+}
+
+/// Returns the TIFF payload of the first APP1 `Exif` segment, reading one
+/// segment at a time and stopping at the start of scan.
+fn exif_segment(mut jpeg: impl Read) -> Option<Vec<u8>> {
+    let mut header = [0u8; 4];
+    jpeg.read_exact(&mut header[..2]).ok()?;
+    if header[..2] != [0xFF, 0xD8] {
+        return None;
+    }
+    loop {
+        jpeg.read_exact(&mut header).ok()?;
+        let marker = header[1];
+        if header[0] != 0xFF || marker == 0xDA {
+            return None;
+        }
+        let len = u64::from(u16::from_be_bytes([header[2], header[3]])).checked_sub(2)?;
+        let mut body = (&mut jpeg).take(len);
+        if marker == 0xE1 {
+            let mut data = Vec::new();
+            body.read_to_end(&mut data).ok()?;
+            if let Some(tiff) = data.strip_prefix(b"Exif\0\0") {
+                return Some(tiff.to_vec());
+            }
+        } else if io::copy(&mut body, &mut io::sink()).ok()? < len {
+            return None;
+        }
+    }
+}
+
+struct Tiff<'a> {
+    data: &'a [u8],
+    little_endian: bool,
+}
+
+impl<'a> Tiff<'a> {
+    fn new(data: &'a [u8]) -> Option<Self> {
+        let little_endian = match data.get(..2)? {
+            b"II" => true,
+            b"MM" => false,
+            _ => return None,
+        };
+        let tiff = Self {
+            data,
+            little_endian,
+        };
+        (tiff.u16(2)? == 42).then_some(tiff)
+    }
+
+    fn u16(&self, offset: u32) -> Option<u16> {
+        let at = offset as usize;
+        let bytes = [*self.data.get(at)?, *self.data.get(at + 1)?];
+        Some(if self.little_endian {
+            u16::from_le_bytes(bytes)
+        } else {
+            u16::from_be_bytes(bytes)
+        })
+    }
+
+    fn u32(&self, offset: u32) -> Option<u32> {
+        let at = offset as usize;
+        let bytes: [u8; 4] = self.data.get(at..at + 4)?.try_into().ok()?;
+        Some(if self.little_endian {
+            u32::from_le_bytes(bytes)
+        } else {
+            u32::from_be_bytes(bytes)
+        })
+    }
+
+    /// Returns the offset of the 12-byte entry for `tag` in the IFD at `ifd`.
+    fn find_entry(&self, ifd: u32, tag: u16) -> Option<u32> {
+        let count = self.u16(ifd)? as u32;
+        (0..count)
+            .map(|i| ifd + 2 + i * 12)
+            .find(|&entry| self.u16(entry) == Some(tag))
+    }
+}
+
+/// Parses `YYYY:MM:DD HH:MM:SS`.
+fn parse_exif_date(text: &str) -> Option<SystemTime> {
+    let num = |range: std::ops::Range<usize>| text.get(range)?.parse::<i64>().ok();
+    let (year, month, day) = (num(0..4)?, num(5..7)?, num(8..10)?);
+    let (hour, minute, second) = (num(11..13)?, num(14..16)?, num(17..19)?);
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) || hour > 23 || minute > 59 {
+        return None;
+    }
+    let secs = days_from_civil(year, month, day) * 86_400 + hour * 3600 + minute * 60 + second;
+    Some(SystemTime::UNIX_EPOCH + Duration::from_secs(u64::try_from(secs).ok()?))
+}
+
+/// Days since 1970-01-01 for a proleptic Gregorian date.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
@@ -0,0 +1,205 @@
+use anyhow::{Context, Result, ensure};
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use serde::{Deserialize, Serialize};
+use stb_image::stb_image::{stbi_info_from_callbacks, stbi_io_callbacks};
+use std::cell::OnceCell;
+use std::fs::{self, File};
+use std::io::{BufReader, Read};
+use std::os::raw::{c_char, c_int, c_void};
+use std::path::Path;
+
+/// Narrows which discovered images get converted.
+///
+/// Glob patterns are matched against the path relative to the input
+/// directory, so `*_raw.*` matches by file name and `**/thumbs/**` by folder.
+/// Size limits are in bytes; `None` disables a limit.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DiscoveryFilter {
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+    pub min_file_size: Option<u64>,
+    pub max_file_size: Option<u64>,
+    pub min_width: Option<u32>,
+    pub min_height: Option<u32>,
+}
+
+impl DiscoveryFilter {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Validates the glob patterns once so every file can be checked cheaply.
+    pub(crate) fn compile(&self) -> Result<CompiledFilter<'_>> {
+        Ok(CompiledFilter {
+            filter: self,
+            include: build_glob_set(&self.include)?,
+            exclude: build_glob_set(&self.exclude)?,
+        })
+    }
+}
+
+pub(crate) struct CompiledFilter<'a> {
+    filter: &'a DiscoveryFilter,
+    include: Option<GlobSet>,
+    exclude: Option<GlobSet>,
+}
+
+impl CompiledFilter<'_> {
+    /// Returns the rule that rejected `path`, or `None` if it should be
+    /// converted. `relative` is the path used for pattern matching.
+    pub(crate) fn rejects(&self, probe: &Probe, relative: &Path) -> Option<String> {
+        let path = probe.path;
+        if let Some(include) = &self.include
+            && !include.is_match(relative)
+        {
+            return Some("no include pattern matched".into());
+        }
+        if let Some(exclude) = &self.exclude {
+            let hit = exclude.matches(relative).into_iter().next();
+            if let Some(i) = hit {
+                return Some(format!("exclude pattern `{}`", self.filter.exclude[i]));
+            }
+        }
+
+        let f = self.filter;
+        if f.min_file_size.is_some() || f.max_file_size.is_some() {
+            let len = fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+            if let Some(min) = f.min_file_size
+                && len < min
+            {
+                return Some(format!("smaller than {min} bytes"));
+            }
+            if let Some(max) = f.max_file_size
+                && len > max
+            {
+                return Some(format!("larger than {max} bytes"));
+            }
+        }
+
+        if f.min_width.is_some() || f.min_height.is_some() {
+            // Unreadable files are left for the converter to report.
+            let Header {
+                width: w,
+                height: h,
+                ..
+            } = probe.header()?;
+            if let Some(min) = f.min_width
+                && w < min
+            {
+                return Some(format!("narrower than {min} px"));
+            }
+            if let Some(min) = f.min_height
+                && h < min
+            {
+                return Some(format!("shorter than {min} px"));
+            }
+        }
+        None
+    }
+}
+
+fn build_glob_set(patterns: &[String]) -> Result<Option<GlobSet>> {
+    if patterns.is_empty() {
+        return Ok(None);
+    }
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        builder.add(Glob::new(pattern).with_context(|| format!("Invalid pattern: {pattern}"))?);
+    }
+    Ok(Some(builder.build()?))
+}
+
+/// A source file whose header is read at most once, however many of the
+/// filter, the memory budget and the decoder ask about it.
+pub(crate) struct Probe<'a> {
+    pub(crate) path: &'a Path,
+    header: OnceCell<Option<Header>>,
+}
+
+impl<'a> Probe<'a> {
+    pub(crate) fn new(path: &'a Path) -> Self {
+        Self {
+            path,
+            header: OnceCell::new(),
+        }
+    }
+
+    /// The source's header, or `None` if it can't be read.
+    pub(crate) fn header(&self) -> Option<Header> {
+        *self.header.get_or_init(|| {
+            let jpeg = crate::is_jpeg(self.path).then(|| crate::downscale::jpeg_header(self.path));
+            // stb_image reads a few JPEGs the scaled decoder rejects.
+            jpeg.flatten().or_else(|| {
+                let (width, height) = image_dimensions(self.path).ok()?;
+                Some(Header {
+                    width,
+                    height,
+                    progressive: false,
+                })
+            })
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Header {
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+    /// Only ever set for JPEGs.
+    pub(crate) progressive: bool,
+}
+
+/// Reads an image's width and height from its header without decoding
+/// pixels, reading only as much of the file as the header takes.
+pub fn image_dimensions(path: &Path) -> Result<(u32, u32)> {
+    let file = File::open(path).with_context(|| format!("Failed to read {:?}", path))?;
+    let mut stream = HeaderStream {
+        reader: BufReader::new(file),
+        eof: false,
+    };
+    let callbacks = stbi_io_callbacks {
+        read: Some(stream_read),
+        skip: Some(stream_skip),
+        eof: Some(stream_eof),
+    };
+    let user = (&mut stream as *mut HeaderStream).cast::<c_void>();
+    let (mut w, mut h, mut comp) = (0, 0, 0);
+    // SAFETY: `stream` outlives the call, and the callbacks reach it only
+    // through `user`.
+    let ok = unsafe { stbi_info_from_callbacks(&callbacks, user, &mut w, &mut h, &mut comp) };
+    ensure!(ok != 0, "Unrecognized image header: {:?}", path);
+    Ok((w as u32, h as u32))
+}
+
+struct HeaderStream {
+    reader: BufReader<File>,
+    eof: bool,
+}
+
+/// SAFETY: `user` must be the `HeaderStream` passed to stb_image and `data`
+/// valid for `size` bytes, as stb_image guarantees.
+unsafe extern "C" fn stream_read(user: *mut c_void, data: *mut c_char, size: c_int) -> c_int {
+    let stream = unsafe { &mut *user.cast::<HeaderStream>() };
+    let buf = unsafe { std::slice::from_raw_parts_mut(data.cast::<u8>(), size.max(0) as usize) };
+    match stream.reader.read(buf) {
+        Ok(n) if n > 0 => n as c_int,
+        _ => {
+            stream.eof = true;
+            0
+        }
+    }
+}
+
+/// SAFETY: as for [`stream_read`].
+unsafe extern "C" fn stream_skip(user: *mut c_void, n: c_int) {
+    let stream = unsafe { &mut *user.cast::<HeaderStream>() };
+    if stream.reader.seek_relative(i64::from(n)).is_err() {
+        stream.eof = true;
+    }
+}
+
+/// SAFETY: as for [`stream_read`].
+unsafe extern "C" fn stream_eof(user: *mut c_void) -> c_int {
+    c_int::from(unsafe { (*user.cast::<HeaderStream>()).eof })
+}
@@ -0,0 +1,409 @@
+//! Queue of independent conversion jobs, each with its own paths and settings.
+
+use crate::settings::JobSettings;
+use crate::{LogFn, MyApp, Progress, RunKind, parse_source_action, progress_bar, split_patterns};
+use eframe::egui::{self, Color32, RichText};
+use std::path::PathBuf;
+use std::sync::atomic::Ordering;
+use std::sync::{Arc, Mutex};
+use webp_converter_app::{
+    ConvertOptions, DiscoveryFilter, RunControl, SourceAction, convert_files_to_webp_threads,
+    convert_to_webp_dir_threads,
+};
+
+/// Everything needed to run one job, captured when it was queued.
+#[derive(Clone)]
+pub struct JobSpec {
+    pub input: String,
+    pub files: Vec<PathBuf>,
+    pub output: String,
+    pub options: ConvertOptions,
+}
+
+impl JobSpec {
+    pub fn run(&self, log_fn: LogFn) -> anyhow::Result<()> {
+        if self.files.is_empty() {
+            convert_to_webp_dir_threads(&self.input, &self.output, &self.options, log_fn)
+        } else {
+            convert_files_to_webp_threads(self.files.clone(), &self.output, &self.options, log_fn)
+        }
+    }
+
+    fn summary(&self) -> String {
+        let source = if self.files.is_empty() {
+            self.input.clone()
+        } else {
+            format!("{} file(s)", self.files.len())
+        };
+        let mut mode = if self.options.auto_lossless {
+            format!("auto, q{}", self.options.quality.round())
+        } else if self.options.lossless {
+            "lossless".to_string()
+        } else {
+            format!("q{}", self.options.quality.round())
+        };
+        if !self.options.filter.is_empty() {
+            mode.push_str(", filtered");
+        }
+        match &self.options.source_action {
+            SourceAction::Keep => {}
+            SourceAction::MoveTo(_) => mode.push_str(", moves originals"),
+            SourceAction::Delete => mode.push_str(", deletes originals"),
+        }
+        if let Some(min) = self.options.min_ssim {
+            mode.push_str(&format!(", SSIM ≥ {min}"));
+        }
+        let target = if self.options.in_place {
+            "in place"
+        } else {
+            &self.output
+        };
+        format!("{source} → {target} ({mode})")
+    }
+}
+
+#[derive(Clone, PartialEq)]
+pub enum JobState {
+    Pending,
+    Held,
+    Running,
+    Done,
+    Failed(String),
+}
+
+pub struct Job {
+    pub id: u64,
+    pub spec: JobSpec,
+    pub state: JobState,
+    pub progress: Arc<Progress>,
+}
+
+impl Job {
+    pub fn new(id: u64, spec: JobSpec, state: JobState) -> Self {
+        Self {
+            id,
+            spec,
+            state,
+            progress: Arc::new(Progress::default()),
+        }
+    }
+
+    /// The persisted form of an unfinished job.
+    pub fn to_settings(&self) -> Option<JobSettings> {
+        let held = match self.state {
+            JobState::Pending | JobState::Running => false,
+            JobState::Held => true,
+            JobState::Done | JobState::Failed(_) => return None,
+        };
+        Some(JobSettings {
+            held,
+            input: self.spec.input.clone(),
+            output: self.spec.output.clone(),
+            files: self.spec.files.clone(),
+            options: self.spec.options.clone(),
+        })
+    }
+
+    pub fn from_settings(settings: JobSettings) -> (JobSpec, JobState) {
+        let state = if settings.held {
+            JobState::Held
+        } else {
+            JobState::Pending
+        };
+        let spec = JobSpec {
+            input: settings.input,
+            files: settings.files,
+            output: settings.output,
+            options: settings.options,
+        };
+        (spec, state)
+    }
+
+    /// Serializes an unfinished job as the `settings` of a journal.
+    pub fn to_journal(&self) -> Option<String> {
+        toml::to_string(&self.to_settings()?).ok()
+    }
+
+    /// Reads [`Job::to_journal`] output, or the tab-separated settings of a
+    /// journal written by an older version.
+    pub fn from_journal(value: &str) -> Option<(JobSpec, JobState)> {
+        match toml::from_str::<JobSettings>(value) {
+            Ok(settings) => Some(Self::from_settings(settings)),
+            Err(_) => Self::from_storage(0, value).map(|job| (job.spec, job.state)),
+        }
+    }
+
+    /// Reads a `job=` line of the legacy settings format. Fields are
+    /// tab-separated; any selected files follow the settings.
+    pub fn from_storage(id: u64, value: &str) -> Option<Self> {
+        let mut fields = value.split('\t');
+        let state = match fields.next()? {
+            "held" => JobState::Held,
+            _ => JobState::Pending,
+        };
+        let input = fields.next()?.to_string();
+        let output = fields.next()?.to_string();
+        let quality = fields.next()?.parse::<f32>().ok()?.clamp(0.0, 100.0);
+        let lossless = fields.next()? == "true";
+        let auto_lossless = fields.next()? == "true";
+        let filter = DiscoveryFilter {
+            include: split_patterns(fields.next()?),
+            exclude: split_patterns(fields.next()?),
+            min_file_size: fields.next()?.parse().ok(),
+            max_file_size: fields.next()?.parse().ok(),
+            min_width: fields.next()?.parse().ok(),
+            min_height: fields.next()?.parse().ok(),
+        };
+        let measure_quality = fields.next()? == "true";
+        let min_ssim = fields.next()?.parse().ok();
+        let raise_quality = fields.next()? == "true";
+        let min_savings = fields.next()?.parse().ok();
+        let copy_original = fields.next()? == "true";
+        let source_action = parse_source_action(fields.next()?);
+        let in_place = fields.next()? == "true";
+        let preserve_times = fields.next()? == "true";
+        let preserve_permissions = fields.next()? == "true";
+        let exif_mtime = fields.next()? == "true";
+        let workers = fields.next()?.parse().ok();
+        let memory_budget = fields.next()?.parse().ok();
+        let max_pixels = fields.next()?.parse().ok();
+        let downscale_large = fields.next()? == "true";
+        let files = fields.map(PathBuf::from).collect();
+        let spec = JobSpec {
+            input,
+            files,
+            output,
+            options: ConvertOptions {
+                quality,
+                lossless,
+                auto_lossless,
+                filter,
+                measure_quality,
+                min_ssim,
+                raise_quality,
+                min_savings,
+                copy_original,
+                source_action,
+                in_place,
+                preserve_times,
+                preserve_permissions,
+                exif_mtime,
+                workers,
+                memory_budget,
+                max_pixels,
+                downscale_large,
+                control: RunControl::default(),
+                journal: None,
+            },
+        };
+        Some(Self::new(id, spec, state))
+    }
+}
+
+/// Marks the first pending job as running and hands back what the runner
+/// needs, so reordering and holds made mid-run are honoured. The spec is
+/// bound to `control` so the app's Pause button covers every job.
+fn claim_next(
+    jobs: &Mutex<Vec<Job>>,
+    control: &RunControl,
+) -> Option<(u64, JobSpec, Arc<Progress>)> {
+    let mut jobs = jobs.lock().ok()?;
+    let job = jobs.iter_mut().find(|j| j.state == JobState::Pending)?;
+    job.state = JobState::Running;
+    job.progress.reset();
+    let mut spec = job.spec.clone();
+    spec.options.control = control.clone();
+    Some((job.id, spec, job.progress.clone()))
+}
+
+fn finish(jobs: &Mutex<Vec<Job>>, id: u64, result: anyhow::Result<()>) {
+    if let Ok(mut jobs) = jobs.lock()
+        && let Some(job) = jobs.iter_mut().find(|j| j.id == id)
+    {
+        job.state = match result {
+            Ok(()) => JobState::Done,
+            Err(e) => JobState::Failed(format!("{e:#}")),
+        };
+    }
+}
+
+impl MyApp {
+    fn current_job_spec(&self) -> Result<JobSpec, String> {
+        if self.files.is_empty() {
+            self.validate()?;
+        } else {
+            self.validate_files()?;
+        }
+        Ok(JobSpec {
+            input: self.input_path.clone(),
+            files: self.files.clone(),
+            output: self.output_path.clone(),
+            options: self.options(),
+        })
+    }
+
+    fn enqueue_current(&mut self) {
+        match self.current_job_spec() {
+            Ok(spec) => {
+                self.validation_error = None;
+                self.push_job(spec, JobState::Pending);
+            }
+            Err(msg) => self.validation_error = Some(msg),
+        }
+    }
+
+    pub(crate) fn push_job(&mut self, spec: JobSpec, state: JobState) {
+        let job = Job::new(self.next_job_id, spec, state);
+        self.next_job_id += 1;
+        if let Ok(mut jobs) = self.jobs.lock() {
+            jobs.push(job);
+        }
+    }
+
+    pub(crate) fn run_jobs_in_thread(&mut self, ctx: &egui::Context) {
+        let jobs = self.jobs.clone();
+        let is_running = self.is_running.clone();
+        let concurrent = self.jobs_concurrent;
+        let control = self.control.clone();
+        let output = self.begin_run(ctx);
+
+        std::thread::spawn(move || {
+            if concurrent {
+                let claimed: Vec<_> = std::iter::from_fn(|| claim_next(&jobs, &control)).collect();
+                std::thread::scope(|scope| {
+                    for (id, spec, job_progress) in claimed {
+                        let log_fn = output.log_fn(Some(job_progress));
+                        let jobs = &jobs;
+                        scope.spawn(move || finish(jobs, id, spec.run(log_fn)));
+                    }
+                });
+            } else {
+                while let Some((id, spec, job_progress)) = claim_next(&jobs, &control) {
+                    let result = spec.run(output.log_fn(Some(job_progress)));
+                    finish(&jobs, id, result);
+                    output.ctx.request_repaint();
+                }
+            }
+
+            is_running.store(false, Ordering::Relaxed);
+            output.ctx.request_repaint();
+        });
+    }
+
+    pub(crate) fn ui_jobs(&mut self, ui: &mut egui::Ui, ctx: &egui::Context) {
+        let running = self.is_running.load(Ordering::Relaxed);
+        let (count, pending) = self.jobs.lock().map_or((0, 0), |jobs| {
+            let pending = jobs.iter().filter(|j| j.state == JobState::Pending).count();
+            (jobs.len(), pending)
+        });
+
+        ui.horizontal(|ui| {
+            if ui.button("Add as job").clicked() {
+                self.enqueue_current();
+            }
+            let run = ui.add_enabled(
+                !running && pending > 0,
+                egui::Button::new(format!("Run jobs ({pending})")),
+            );
+            if run.clicked() {
+                self.request_run(RunKind::Jobs, ctx);
+            }
+            ui.add_enabled_ui(!running, |ui| {
+                ui.checkbox(&mut self.jobs_concurrent, "Concurrently");
+            });
+            if ui.button("Clear finished").clicked()
+                && let Ok(mut jobs) = self.jobs.lock()
+            {
+                jobs.retain(|j| !matches!(j.state, JobState::Done | JobState::Failed(_)));
+            }
+        });
+        if count == 0 {
+            return;
+        }
+
+        let header = RichText::new(format!("Jobs ({count})")).size(14.0);
+        egui::CollapsingHeader::new(header)
+            .id_salt("job_queue")
+            .default_open(true)
+            .show(ui, |ui| {
+                egui::ScrollArea::vertical()
+                    .id_salt("job_queue_scroll")
+                    .max_height(120.0)
+                    .show(ui, |ui| self.ui_job_rows(ui));
+            });
+    }
+
+    fn ui_job_rows(&mut self, ui: &mut egui::Ui) {
+        enum Action {
+            Up(usize),
+            Down(usize),
+            ToggleHold(usize),
+            Remove(usize),
+        }
+
+        let Ok(mut jobs) = self.jobs.lock() else {
+            return;
+        };
+        let mut action = None;
+        let len = jobs.len();
+        for (i, job) in jobs.iter().enumerate() {
+            let idle = job.state != JobState::Running;
+            ui.horizontal(|ui| {
+                let (icon, color) = match &job.state {
+                    JobState::Pending => ("⏳", Color32::LIGHT_GRAY),
+                    JobState::Held => ("⏸", Color32::from_rgb(200, 180, 120)),
+                    JobState::Running => ("▶", Color32::LIGHT_BLUE),
+                    JobState::Done => ("✔", Color32::from_rgb(150, 220, 150)),
+                    JobState::Failed(_) => ("✖", Color32::from_rgb(240, 120, 120)),
+                };
+                let status = ui.label(RichText::new(icon).size(13.0).color(color));
+                if let JobState::Failed(msg) = &job.state {
+                    status.on_hover_text(msg);
+                }
+                if small_button(ui, idle && i > 0, "▲") {
+                    action = Some(Action::Up(i));
+                }
+                if small_button(ui, idle && i + 1 < len, "▼") {
+                    action = Some(Action::Down(i));
+                }
+                let holdable = matches!(job.state, JobState::Pending | JobState::Held);
+                let hold_label = if job.state == JobState::Held {
+                    "▶"
+                } else {
+                    "⏸"
+                };
+                if small_button(ui, holdable, hold_label) {
+                    action = Some(Action::ToggleHold(i));
+                }
+                if small_button(ui, idle, "✖") {
+                    action = Some(Action::Remove(i));
+                }
+                ui.label(RichText::new(job.spec.summary()).size(12.0));
+            });
+            progress_bar(ui, &job.progress, job.state == JobState::Running);
+        }
+
+        // A neighbour may have started running since the frame was laid out.
+        let movable = |jobs: &[Job], i: usize| jobs[i].state != JobState::Running;
+        match action {
+            Some(Action::Up(i)) if movable(&jobs, i - 1) => jobs.swap(i, i - 1),
+            Some(Action::Down(i)) if movable(&jobs, i + 1) => jobs.swap(i, i + 1),
+            Some(Action::ToggleHold(i)) => {
+                jobs[i].state = match jobs[i].state {
+                    JobState::Held => JobState::Pending,
+                    JobState::Pending => JobState::Held,
+                    ref other => other.clone(),
+                };
+            }
+            Some(Action::Remove(i)) if movable(&jobs, i) => {
+                jobs.remove(i);
+            }
+            _ => {}
+        }
+    }
+}
+
+fn small_button(ui: &mut egui::Ui, enabled: bool, label: &str) -> bool {
+    ui.add_enabled(enabled, egui::Button::new(label).small())
+        .clicked()
+}
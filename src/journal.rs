@@ -0,0 +1,179 @@
+use anyhow::{Context, Result};
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Append-only record of a batch, so a run cut short by a crash or reboot
+/// can be resumed.
+///
+/// The file holds one `settings` line chosen by the caller, a `file` line
+/// per discovered source and a `done` line per finished one. Directory runs
+/// add a `scan` line naming the directory, and a `scanned` line once every
+/// source in it was discovered. Values are escaped so settings and paths
+/// may contain line breaks.
+///
+/// Lines are buffered and flushed at most every [`FLUSH_INTERVAL`]. Since
+/// they reach the file in order, a crash only loses the newest lines: their
+/// files are discovered or converted again on resume.
+#[derive(Debug)]
+pub struct Journal {
+    path: PathBuf,
+    file: Mutex<(BufWriter<File>, Instant)>,
+}
+
+impl Journal {
+    /// Starts a fresh journal at `path`, replacing any previous one.
+    pub fn create(path: &Path, settings: &str) -> Result<Self> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let file =
+            File::create(path).with_context(|| format!("Failed to create journal {:?}", path))?;
+        let mut file = BufWriter::new(file);
+        writeln!(file, "{FORMAT_LINE}")?;
+        writeln!(file, "settings\t{}", escape(settings))?;
+        file.flush()?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            file: Mutex::new((file, Instant::now())),
+        })
+    }
+
+    pub(crate) fn discovered(&self, entries: &[PathBuf]) {
+        for path in entries {
+            self.append("file", path);
+        }
+    }
+
+    pub(crate) fn completed(&self, path: &Path) {
+        self.append("done", path);
+    }
+
+    pub(crate) fn scan_started(&self, input_dir: &Path) {
+        self.append("scan", input_dir);
+    }
+
+    pub(crate) fn scan_finished(&self) {
+        self.append("scanned", Path::new(""));
+    }
+
+    /// Deletes the journal once its run has ended.
+    pub fn remove(&self) -> Result<()> {
+        fs::remove_file(&self.path)
+            .with_context(|| format!("Failed to remove journal {:?}", self.path))
+    }
+
+    fn append(&self, kind: &str, path: &Path) {
+        // Journaling is best effort; a failed write must not fail the run.
+        if let Ok(mut guard) = self.file.lock() {
+            let (file, last_flush) = &mut *guard;
+            let _ = writeln!(file, "{kind}\t{}", escape(&path.display().to_string()));
+            if last_flush.elapsed() >= FLUSH_INTERVAL {
+                let _ = file.flush();
+                *last_flush = Instant::now();
+            }
+        }
+    }
+}
+
+/// First line of journals whose values are escaped.
+const FORMAT_LINE: &str = "journal\t2";
+
+/// Longest a journal line waits in memory before it is written out.
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+}
+
+fn unescape(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => out.push('\n'),
+            Some('r') => out.push('\r'),
+            Some(other) => out.push(other),
+            None => {}
+        }
+    }
+    out
+}
+
+/// What a journal left behind by an interrupted run says.
+#[derive(Debug, Clone, Default)]
+pub struct JournalState {
+    pub settings: String,
+    pub discovered: Vec<PathBuf>,
+    pub completed: HashSet<PathBuf>,
+    /// The directory a directory run was scanning.
+    pub scan: Option<PathBuf>,
+    /// The scan reached the end, so `discovered` lists every source.
+    pub scan_complete: bool,
+}
+
+impl JournalState {
+    /// Reads the journal at `path`, or `None` if there is none.
+    pub fn load(path: &Path) -> Result<Option<Self>> {
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e).with_context(|| format!("Failed to open {:?}", path)),
+        };
+        let mut state = Self::default();
+        // Journals from before escaping hold values verbatim.
+        let mut escaped = false;
+        for line in BufReader::new(file).lines() {
+            // A crash can leave the last line half written.
+            let Ok(line) = line else { break };
+            if line == FORMAT_LINE {
+                escaped = true;
+                continue;
+            }
+            let Some((kind, value)) = line.split_once('\t') else {
+                continue;
+            };
+            let value = if escaped {
+                unescape(value)
+            } else {
+                value.to_string()
+            };
+            match kind {
+                "settings" => state.settings = value,
+                "file" => state.discovered.push(PathBuf::from(value)),
+                "done" => {
+                    state.completed.insert(PathBuf::from(value));
+                }
+                "scan" => state.scan = Some(PathBuf::from(value)),
+                "scanned" => state.scan_complete = true,
+                _ => {}
+            }
+        }
+        Ok(Some(state))
+    }
+
+    /// The directory to scan again on resume, because the run ended before
+    /// all of its sources were discovered.
+    pub fn unfinished_scan(&self) -> Option<&Path> {
+        self.scan.as_deref().filter(|_| !self.scan_complete)
+    }
+
+    /// Discovered sources that never finished, in discovery order.
+    pub fn remaining(&self) -> Vec<PathBuf> {
+        self.discovered
+            .iter()
+            .filter(|p| !self.completed.contains(*p))
+            .cloned()
+            .collect()
+    }
+}
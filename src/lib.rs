@@ -1,33 +1,317 @@
 use anyhow::{Context, Result, anyhow, ensure};
+use budget::MemoryBudget;
+use filter::{Header, Probe};
 use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
 use stb_image::image::{LoadResult, load_with_depth};
+use std::collections::{HashMap, HashSet};
 use std::fs;
-use std::fs::File;
-use std::io::{BufWriter, Write};
+use std::fs::{File, FileTimes};
+use std::io::{self, BufWriter, Write};
 use std::path::{Path, PathBuf};
-use std::sync::atomic::{AtomicUsize, Ordering};
-use webp::Encoder;
+use std::sync::{Arc, Mutex, mpsc};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+use webp::{Decoder, Encoder};
 
-const SUPPORTED_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "bmp", "tiff", "gif"];
+mod budget;
+mod control;
+mod downscale;
+mod exif;
+mod filter;
+mod journal;
+mod metrics;
+mod preset;
+mod watch;
+
+pub use control::RunControl;
+pub use filter::{DiscoveryFilter, image_dimensions};
+pub use journal::{Journal, JournalState};
+pub use metrics::{QualityScores, compare_images};
+pub use preset::Preset;
+pub use watch::watch_dir_threads;
+
+pub const SUPPORTED_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "bmp", "tiff", "gif"];
+
+/// Sources found between two incremental `Discovered` events.
+const DISCOVERY_BATCH: usize = 500;
+/// Sources the scanner may run ahead of the workers.
+const DISCOVERY_QUEUE: usize = 4096;
 
 #[derive(Debug, Clone)]
 pub enum LogEvent {
     Started { input_dir: String },
-    Discovered { total: usize },
-    Converted { path: PathBuf },
+    /// `found` more sources were discovered, `total` so far. The last
+    /// update of a run has `complete` set.
+    Discovered {
+        found: usize,
+        total: usize,
+        complete: bool,
+    },
+    Watching { input_dir: String },
+    Detected { path: PathBuf },
+    /// Workers stopped picking up new files.
+    Paused,
+    Resumed,
+    Converted { path: PathBuf, result: Conversion },
     Skipped { path: PathBuf, reason: SkipReason },
-    Error { msg: String },
+    /// A source was removed after its output verified; `moved_to` is set
+    /// when it was archived rather than deleted.
+    SourceRemoved { path: PathBuf, moved_to: Option<PathBuf> },
+    Error { path: Option<PathBuf>, msg: String },
     Finished {
         success: usize,
         skipped: usize,
         errors: usize,
         total: usize,
+        /// Wall-clock time from the start of the run, pauses included.
+        elapsed: Duration,
+        /// Source bytes of every file that was decoded.
+        bytes_read: u64,
     },
 }
 
 #[derive(Debug, Clone)]
 pub enum SkipReason {
     OutputExists,
+    /// The output path names another source of the same run.
+    OutputIsInput,
+    /// Another source of the same run, such as `a.png` beside `a.bmp`,
+    /// already writes this output.
+    OutputClaimed { by: PathBuf },
+    Filtered { rule: String },
+    /// The WebP did not save enough over its source and was discarded.
+    NotSmaller {
+        source_len: u64,
+        output_len: u64,
+        /// Whether the source was copied into the output directory in its
+        /// place.
+        original: OriginalCopy,
+    },
+    /// The source has more pixels than [`ConvertOptions::max_pixels`].
+    TooLarge { width: u32, height: u32 },
+}
+
+/// What [`ConvertOptions::copy_original`] did for a source whose WebP was
+/// dropped.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OriginalCopy {
+    /// Not requested, or the output directory is the source's own.
+    NotCopied,
+    Copied,
+    /// A file of that name already exists there and was left alone.
+    NameTaken,
+}
+
+/// Result of [`convert_file`] for a source that decoded and encoded fine.
+#[derive(Debug, Clone)]
+pub enum FileOutcome {
+    Converted(Conversion),
+    Skipped(SkipReason),
+}
+
+/// Details of one successfully written output.
+#[derive(Debug, Clone)]
+pub struct Conversion {
+    /// Whether the output was encoded losslessly; with
+    /// [`ConvertOptions::auto_lossless`] this is the mode that won.
+    pub lossless: bool,
+    /// Quality actually used; higher than requested after a fallback.
+    pub quality: f32,
+    pub scores: Option<QualityScores>,
+    /// The output still scores below [`ConvertOptions::min_ssim`].
+    pub below_min_ssim: bool,
+    /// Size of the source in bytes.
+    pub source_len: u64,
+    /// Size of the written WebP in bytes.
+    pub output_len: u64,
+}
+
+/// What happens to a source once its output is written and decodes.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SourceAction {
+    #[default]
+    Keep,
+    /// Move the source into this folder.
+    MoveTo(PathBuf),
+    Delete,
+}
+
+/// Settings shared by every batch entry point. Serializes without the
+/// per-run `control` and `journal`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ConvertOptions {
+    #[serde(serialize_with = "serialize_f32")]
+    pub quality: f32,
+    pub lossless: bool,
+    /// Encode each image both ways and keep the smaller output, as long as
+    /// the lossy one meets `min_ssim`. Overrides `lossless`.
+    pub auto_lossless: bool,
+    pub filter: DiscoveryFilter,
+    /// Decode every output and score it against its source.
+    pub measure_quality: bool,
+    /// Outputs with a lower SSIM are flagged. Implies `measure_quality`.
+    pub min_ssim: Option<f64>,
+    /// Re-encode at a higher quality until `min_ssim` is met instead of
+    /// only flagging the output.
+    pub raise_quality: bool,
+    /// Minimum size reduction in percent. Outputs that save less are not
+    /// written; `None` keeps every output.
+    #[serde(serialize_with = "serialize_opt_f32")]
+    pub min_savings: Option<f32>,
+    /// Copy the source into the output directory when its WebP is dropped
+    /// by `min_savings`, so the output folder stays complete.
+    pub copy_original: bool,
+    /// Applied to each source after a successful conversion only.
+    pub source_action: SourceAction,
+    /// Write each output beside its source instead of into the output
+    /// directory, which may then be left empty.
+    pub in_place: bool,
+    /// Copy the source's modified and accessed times onto the output.
+    pub preserve_times: bool,
+    /// Copy the source's permissions onto the output.
+    pub preserve_permissions: bool,
+    /// Set the output's modified time from EXIF `DateTimeOriginal` when the
+    /// source has one; takes precedence over `preserve_times`.
+    pub exif_mtime: bool,
+    /// Number of conversion threads; `None` uses one per core.
+    pub workers: Option<usize>,
+    /// Upper bound in bytes on decoded RGBA buffers held at once. Large
+    /// images wait for others to finish rather than all decoding together.
+    pub memory_budget: Option<u64>,
+    /// Sources with more pixels are skipped before decoding; `None` decodes
+    /// everything.
+    pub max_pixels: Option<u64>,
+    /// Shrink JPEGs over `max_pixels` to fit instead of skipping them.
+    pub downscale_large: bool,
+    /// Pause handle checked before each file is started.
+    #[serde(skip)]
+    pub control: RunControl,
+    /// Records discovered and finished files so the run can be resumed.
+    #[serde(skip)]
+    pub journal: Option<Arc<Journal>>,
+}
+
+/// Quality added per re-encode when an output falls below `min_ssim`.
+const QUALITY_STEP: f32 = 5.0;
+
+impl Default for ConvertOptions {
+    fn default() -> Self {
+        Self {
+            quality: 87.0,
+            lossless: false,
+            auto_lossless: false,
+            filter: DiscoveryFilter::default(),
+            measure_quality: false,
+            min_ssim: None,
+            raise_quality: false,
+            min_savings: None,
+            copy_original: false,
+            source_action: SourceAction::Keep,
+            in_place: false,
+            preserve_times: false,
+            preserve_permissions: false,
+            exif_mtime: false,
+            workers: None,
+            memory_budget: None,
+            max_pixels: None,
+            downscale_large: false,
+            control: RunControl::default(),
+            journal: None,
+        }
+    }
+}
+
+/// Writes `v` as the `f64` with the same shortest decimal form, so 0.1 is
+/// stored as `0.1` rather than `0.10000000149011612`.
+fn serialize_f32<S: serde::Serializer>(v: &f32, s: S) -> std::result::Result<S::Ok, S::Error> {
+    s.serialize_f64(v.to_string().parse().unwrap_or(f64::from(*v)))
+}
+
+fn serialize_opt_f32<S: serde::Serializer>(
+    v: &Option<f32>,
+    s: S,
+) -> std::result::Result<S::Ok, S::Error> {
+    match v {
+        Some(v) => serialize_f32(v, s),
+        None => s.serialize_none(),
+    }
+}
+
+impl ConvertOptions {
+    /// Creates `output_dir` unless outputs go beside their sources.
+    fn prepare_output_dir(&self, output_dir: &str) -> Result<()> {
+        if self.in_place {
+            return Ok(());
+        }
+        ensure!(!output_dir.is_empty(), "Output path is empty.");
+        fs::create_dir_all(output_dir)?;
+        Ok(())
+    }
+
+    /// Builds the pool a run converts on, so it never takes over rayon's
+    /// global one.
+    fn thread_pool(&self) -> Result<rayon::ThreadPool> {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(self.workers.unwrap_or(0))
+            .thread_name(|i| format!("webp-worker-{i}"))
+            .build()
+            .context("Failed to start worker threads")
+    }
+
+    /// How the probed source would be decoded under `max_pixels`, from its
+    /// header alone. Unreadable headers are left for the decoder to report.
+    fn plan_decode(&self, probe: &Probe) -> Decode {
+        let Some(limit) = self.max_pixels else {
+            return Decode::Full;
+        };
+        let Some(header) = probe.header() else {
+            return Decode::Full;
+        };
+        let Header { width, height, .. } = header;
+        if u64::from(width) * u64::from(height) <= limit {
+            Decode::Full
+        } else if self.downscale_large && is_jpeg(probe.path) {
+            Decode::Downscaled(header)
+        } else {
+            Decode::Refused { width, height }
+        }
+    }
+
+    /// Bytes of memory `plan` is expected to need while decoding.
+    fn decode_bytes(&self, probe: &Probe, plan: &Decode) -> u64 {
+        match plan {
+            Decode::Full => probe
+                .header()
+                .map_or(0, |h| u64::from(h.width) * u64::from(h.height) * 4),
+            Decode::Downscaled(header) => {
+                downscale::peak_bytes(*header, self.max_pixels.unwrap_or(u64::MAX))
+            }
+            Decode::Refused { .. } => 0,
+        }
+    }
+
+    /// Where the WebP for `path` is written.
+    fn output_path(&self, path: &Path, output_dir: &Path) -> PathBuf {
+        match path.parent() {
+            Some(parent) if self.in_place => output_path_for(path, parent),
+            _ => output_path_for(path, output_dir),
+        }
+    }
+}
+
+enum Decode {
+    Full,
+    Downscaled(Header),
+    Refused { width: u32, height: u32 },
+}
+
+fn is_jpeg(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("jpg") || ext.eq_ignore_ascii_case("jpeg"))
 }
 
 pub fn convert_to_webp(
@@ -36,6 +320,32 @@ pub fn convert_to_webp(
     quality: f32,
     lossless: bool,
 ) -> Result<()> {
+    let options = ConvertOptions {
+        quality,
+        lossless,
+        ..ConvertOptions::default()
+    };
+    convert_file(input_path, output_path, &options).map(|_| ())
+}
+
+/// Converts one image using every encoding setting in `options`, scoring
+/// the output when quality measurement is requested.
+pub fn convert_file(
+    input_path: &Path,
+    output_path: &Path,
+    options: &ConvertOptions,
+) -> Result<FileOutcome> {
+    let plan = options.plan_decode(&Probe::new(input_path));
+    convert_planned(input_path, output_path, options, plan)
+}
+
+/// [`convert_file`] with the decode already planned from the header.
+fn convert_planned(
+    input_path: &Path,
+    output_path: &Path,
+    options: &ConvertOptions,
+    plan: Decode,
+) -> Result<FileOutcome> {
     ensure!(
         input_path != output_path,
         "Input and output paths must differ."
@@ -48,115 +358,798 @@ pub fn convert_to_webp(
         .to_ascii_lowercase();
     ensure!(ext != "webp", "Input is already a WebP image.");
 
-    let img_data = match load_with_depth(input_path, 4, false) {
-        LoadResult::ImageU8(data) => data,
-        LoadResult::Error(msg) => {
-            return Err(anyhow!("stb_image failed for {:?}: {}", input_path, msg));
+    // Read before decoding, which may update the access time.
+    let source_meta = fs::metadata(input_path)?;
+    let image = match plan {
+        Decode::Full => load_rgba(input_path)?,
+        Decode::Downscaled(_) => {
+            downscale::load_jpeg_within(input_path, options.max_pixels.unwrap_or(u64::MAX))?
+        }
+        Decode::Refused { width, height } => {
+            return Ok(FileOutcome::Skipped(SkipReason::TooLarge { width, height }));
+        }
+    };
+    let (webp, conversion) = if options.auto_lossless {
+        let lossless = encode_checked(&image, options, true)?;
+        let lossy = encode_checked(&image, options, false)?;
+        if !lossy.1.below_min_ssim && lossy.0.len() < lossless.0.len() {
+            lossy
+        } else {
+            lossless
+        }
+    } else {
+        encode_checked(&image, options, options.lossless)?
+    };
+
+    let source_len = source_meta.len();
+    if let Some(margin) = options.min_savings {
+        let output_len = webp.len() as u64;
+        let limit = source_len as f64 * (1.0 - f64::from(margin) / 100.0);
+        if output_len as f64 >= limit {
+            let original = if options.copy_original {
+                copy_original(input_path, output_path)?
+            } else {
+                OriginalCopy::NotCopied
+            };
+            return Ok(FileOutcome::Skipped(SkipReason::NotSmaller {
+                source_len,
+                output_len,
+                original,
+            }));
         }
-        _ => return Err(anyhow!("Unsupported format: {:?}", input_path)),
+    }
+
+    write_webp_atomically(output_path, &webp)?;
+    copy_metadata(&source_meta, input_path, output_path, options)?;
+
+    Ok(FileOutcome::Converted(Conversion {
+        source_len,
+        ..conversion
+    }))
+}
+
+/// Carries timestamps and permissions over from the source as requested;
+/// `meta` is the source's metadata from before it was read.
+fn copy_metadata(
+    meta: &fs::Metadata,
+    input_path: &Path,
+    output_path: &Path,
+    options: &ConvertOptions,
+) -> Result<()> {
+    if !options.preserve_times && !options.preserve_permissions && !options.exif_mtime {
+        return Ok(());
+    }
+    let mut times = FileTimes::new();
+    if options.preserve_times {
+        times = times.set_modified(meta.modified()?).set_accessed(meta.accessed()?);
+    }
+    if options.exif_mtime
+        && let Some(taken) = exif::date_time_original(input_path)
+    {
+        times = times.set_modified(taken);
+    }
+    File::options()
+        .write(true)
+        .open(output_path)
+        .and_then(|file| file.set_times(times))
+        .with_context(|| format!("Failed to set times on {:?}", output_path))?;
+
+    // Last, since a read-only source makes the output read-only too.
+    if options.preserve_permissions {
+        fs::set_permissions(output_path, meta.permissions())
+            .with_context(|| format!("Failed to set permissions on {:?}", output_path))?;
+    }
+    Ok(())
+}
+
+/// Writes `webp` to a temporary file beside `output_path`, syncs it, checks
+/// that it decodes, and only then renames it into place, so an interrupted
+/// run never leaves a truncated output behind.
+fn write_webp_atomically(output_path: &Path, webp: &[u8]) -> Result<()> {
+    let name = output_path
+        .file_name()
+        .with_context(|| format!("Output has no file name: {:?}", output_path))?;
+    let mut temp_name = std::ffi::OsString::from(".");
+    temp_name.push(name);
+    // Unique per write, so concurrent writers never share a temp file.
+    let n = NEXT_TEMP.fetch_add(1, Ordering::Relaxed);
+    temp_name.push(format!(".{}.{n}.tmp", std::process::id()));
+    let temp_path = output_path.with_file_name(temp_name);
+
+    let result = write_and_verify(&temp_path, webp).and_then(|()| {
+        fs::rename(&temp_path, output_path)
+            .with_context(|| format!("Failed to move output into place: {:?}", output_path))
+    });
+    if result.is_err() {
+        let _ = fs::remove_file(&temp_path);
+    }
+    result
+}
+
+/// Numbers the temp files of [`write_webp_atomically`].
+static NEXT_TEMP: AtomicU64 = AtomicU64::new(0);
+
+/// Deletes the `.<name>.webp.<pid>.<n>.tmp` files that an interrupted
+/// [`write_webp_atomically`] left in `dir`, and nothing else.
+fn remove_stale_temps(dir: &Path) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
     };
+    let digits = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+    for entry in entries.flatten() {
+        let name = entry.file_name();
+        let Some(inner) = name.to_str().and_then(|n| n.strip_prefix('.')?.strip_suffix(".tmp"))
+        else {
+            continue;
+        };
+        let mut parts = inner.rsplitn(3, '.');
+        if let (Some(n), Some(pid), Some(output)) = (parts.next(), parts.next(), parts.next())
+            && output.ends_with(".webp")
+            && digits(pid)
+            && digits(n)
+        {
+            let _ = fs::remove_file(entry.path());
+        }
+    }
+}
 
-    let encoder = Encoder::from_rgba(
-        &img_data.data,
-        img_data.width as u32,
-        img_data.height as u32,
+fn write_and_verify(path: &Path, webp: &[u8]) -> Result<()> {
+    let file =
+        File::create_new(path).with_context(|| format!("Failed to create file: {:?}", path))?;
+    let mut writer = BufWriter::new(file);
+    writer
+        .write_all(webp)
+        .and_then(|()| writer.flush())
+        .with_context(|| format!("Failed to write {:?}", path))?;
+    writer
+        .get_ref()
+        .sync_all()
+        .with_context(|| format!("Failed to sync {:?} to disk", path))?;
+
+    let written = fs::read(path).with_context(|| format!("Failed to read back {:?}", path))?;
+    ensure!(
+        written.len() == webp.len(),
+        "Output is truncated: {} of {} bytes written",
+        written.len(),
+        webp.len()
     );
+    decode_webp(&written).context("Output failed WebP verification")?;
+    Ok(())
+}
+
+/// Copies `input_path` beside `output_path` under its own file name, never
+/// replacing a file already there, such as another source of the run.
+fn copy_original(input_path: &Path, output_path: &Path) -> Result<OriginalCopy> {
+    let Some(name) = input_path.file_name() else {
+        return Ok(OriginalCopy::NotCopied);
+    };
+    let target = output_path.with_file_name(name);
+    if target == input_path {
+        return Ok(OriginalCopy::NotCopied);
+    }
+    let mut source =
+        File::open(input_path).with_context(|| format!("Failed to open {:?}", input_path))?;
+    let mut copy = match File::create_new(&target) {
+        Ok(file) => file,
+        Err(e) if e.kind() == io::ErrorKind::AlreadyExists => return Ok(OriginalCopy::NameTaken),
+        Err(e) => return Err(e).with_context(|| format!("Failed to create {:?}", target)),
+    };
+    if let Err(e) = io::copy(&mut source, &mut copy) {
+        let _ = fs::remove_file(&target);
+        return Err(e).with_context(|| format!("Failed to copy original to {:?}", target));
+    }
+    Ok(OriginalCopy::Copied)
+}
+
+/// Encodes `image` in one mode, scoring it and stepping the quality up
+/// as `options` require.
+fn encode_checked(
+    image: &RgbaImage,
+    options: &ConvertOptions,
+    lossless: bool,
+) -> Result<(Vec<u8>, Conversion)> {
+    let mut quality = options.quality.clamp(0.0, 100.0);
+    loop {
+        let webp = encode_webp(image, quality, lossless);
+        if !options.measure_quality && options.min_ssim.is_none() {
+            let conversion = Conversion {
+                lossless,
+                quality,
+                scores: None,
+                below_min_ssim: false,
+                source_len: 0,
+                output_len: webp.len() as u64,
+            };
+            return Ok((webp, conversion));
+        }
+        let scores = compare_images(image, &decode_webp(&webp)?)?;
+        let below = options.min_ssim.is_some_and(|min| scores.ssim < min);
+        if below && options.raise_quality && !lossless && quality < 100.0 {
+            quality = (quality + QUALITY_STEP).min(100.0);
+            continue;
+        }
+        let conversion = Conversion {
+            lossless,
+            quality,
+            scores: Some(scores),
+            below_min_ssim: below,
+            source_len: 0,
+            output_len: webp.len() as u64,
+        };
+        return Ok((webp, conversion));
+    }
+}
+
+/// An 8-bit RGBA image held in memory.
+#[derive(Debug, Clone)]
+pub struct RgbaImage {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+/// Decodes any of the [`SUPPORTED_EXTENSIONS`] into RGBA.
+pub fn load_rgba(path: &Path) -> Result<RgbaImage> {
+    match load_with_depth(path, 4, false) {
+        LoadResult::ImageU8(data) => Ok(RgbaImage {
+            width: data.width as u32,
+            height: data.height as u32,
+            pixels: data.data,
+        }),
+        LoadResult::Error(msg) => Err(anyhow!("stb_image failed for {:?}: {}", path, msg)),
+        _ => Err(anyhow!("Unsupported format: {:?}", path)),
+    }
+}
+
+/// Encodes `image` in memory; `quality` is ignored when `lossless` is set.
+pub fn encode_webp(image: &RgbaImage, quality: f32, lossless: bool) -> Vec<u8> {
+    let encoder = Encoder::from_rgba(&image.pixels, image.width, image.height);
     let webp = if lossless {
         encoder.encode_lossless()
     } else {
         encoder.encode(quality.clamp(0.0, 100.0))
     };
+    webp.to_vec()
+}
 
-    let mut writer = BufWriter::new(
-        File::create(output_path)
-            .with_context(|| format!("Failed to create file: {:?}", output_path))?,
-    );
-    writer.write_all(&webp)?;
-
-    Ok(())
+/// Decodes a WebP bitstream, expanding opaque images to RGBA.
+pub fn decode_webp(data: &[u8]) -> Result<RgbaImage> {
+    let decoded = Decoder::new(data)
+        .decode()
+        .ok_or_else(|| anyhow!("Invalid WebP data"))?;
+    let pixels = if decoded.is_alpha() {
+        decoded.to_vec()
+    } else {
+        decoded
+            .chunks_exact(3)
+            .flat_map(|rgb| [rgb[0], rgb[1], rgb[2], 255])
+            .collect()
+    };
+    Ok(RgbaImage {
+        width: decoded.width(),
+        height: decoded.height(),
+        pixels,
+    })
 }
 
 pub fn convert_to_webp_dir_threads<F>(
     input_dir: &str,
     output_dir: &str,
-    quality: f32,
-    lossless: bool,
+    options: &ConvertOptions,
     log_fn: F,
 ) -> anyhow::Result<()>
+where
+    F: Fn(LogEvent) + Sync + Send,
+{
+    convert_dir(input_dir, output_dir, options, &HashSet::new(), log_fn)
+}
+
+/// Converts the supported images in `input_dir`, except those in `skip`.
+fn convert_dir<F>(
+    input_dir: &str,
+    output_dir: &str,
+    options: &ConvertOptions,
+    skip: &HashSet<PathBuf>,
+    log_fn: F,
+) -> Result<()>
 where
     F: Fn(LogEvent) + Sync + Send,
 {
     ensure!(!input_dir.is_empty(), "Input path is empty.");
-    ensure!(!output_dir.is_empty(), "Output path is empty.");
 
+    // `Started` opens a scan that only `Discovered { complete: true }`
+    // closes, so it waits until nothing else can fail.
+    options.prepare_output_dir(output_dir)?;
+    let output_dir = Path::new(output_dir);
+    let filter = options.filter.compile()?;
+    let dir = fs::read_dir(input_dir)?;
+    let pool = options.thread_pool()?;
     log_fn(LogEvent::Started {
         input_dir: input_dir.to_string(),
     });
-    fs::create_dir_all(output_dir)?;
-    let output_dir = Path::new(output_dir);
+    let tally = Tally::new(options);
+    let log_fn = &log_fn;
+
+    // The scanner feeds workers as it goes, so huge folders start converting
+    // right away. Filters run on the workers since they may read headers.
+    let (tx, rx) = mpsc::sync_channel::<PathBuf>(DISCOVERY_QUEUE);
+    let total = std::thread::scope(|scope| {
+        let scanner = scope.spawn(move || {
+            let (mut total, mut reported) = (0, 0);
+            if let Some(journal) = &options.journal {
+                journal.scan_started(Path::new(input_dir));
+            }
+            let mut complete = true;
+            for path in dir.filter_map(Result::ok).map(|entry| entry.path()) {
+                if !path.is_file() || !is_supported_image(&path) || skip.contains(&path) {
+                    continue;
+                }
+                if let Some(journal) = &options.journal {
+                    journal.discovered(std::slice::from_ref(&path));
+                }
+                if tx.send(path).is_err() {
+                    complete = false;
+                    break;
+                }
+                total += 1;
+                if total - reported == DISCOVERY_BATCH {
+                    log_fn(LogEvent::Discovered {
+                        found: total - reported,
+                        total,
+                        complete: false,
+                    });
+                    reported = total;
+                }
+            }
+            if complete && let Some(journal) = &options.journal {
+                journal.scan_finished();
+            }
+            log_fn(LogEvent::Discovered {
+                found: total - reported,
+                total,
+                complete: true,
+            });
+            total
+        });
+
+        pool.install(|| {
+            rx.into_iter().par_bridge().for_each(|path| {
+                options.control.checkpoint(log_fn);
+                let relative = path.strip_prefix(input_dir).unwrap_or(&path);
+                let probe = Probe::new(&path);
+                if let Some(rule) = filter.rejects(&probe, relative) {
+                    tally.skip(&path, SkipReason::Filtered { rule }, log_fn);
+                    return;
+                }
+                let output_path = options.output_path(&path, output_dir);
+                convert_unless_exists(&probe, &output_path, options, &tally, log_fn);
+            });
+        });
+        scanner.join().unwrap_or(0)
+    });
+
+    log_fn(tally.finished(total));
+    Ok(())
+}
 
-    let entries: Vec<PathBuf> = fs::read_dir(input_dir)?
-        .filter_map(Result::ok)
-        .map(|entry| entry.path())
-        .filter(|path| {
-            path.is_file()
-                && path
-                    .extension()
-                    .and_then(|e| e.to_str())
-                    .map_or(false, |ext| {
-                        let lower = ext.to_ascii_lowercase();
-                        SUPPORTED_EXTENSIONS.contains(&lower.as_str())
-                    })
+/// Converts an explicit list of files into `output_dir`, regardless of where
+/// each source lives, or beside each one in in-place mode.
+pub fn convert_files_to_webp_threads<F>(
+    files: Vec<PathBuf>,
+    output_dir: &str,
+    options: &ConvertOptions,
+    log_fn: F,
+) -> anyhow::Result<()>
+where
+    F: Fn(LogEvent) + Sync + Send,
+{
+    ensure!(!files.is_empty(), "No input files selected.");
+
+    options.prepare_output_dir(output_dir)?;
+    log_fn(LogEvent::Started {
+        input_dir: describe_sources(&files),
+    });
+
+    convert_entries(&files, Path::new(output_dir), options, &log_fn)
+}
+
+/// Returns `true` if `path` has one of the [`SUPPORTED_EXTENSIONS`].
+pub fn is_supported_image(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|ext| {
+            let lower = ext.to_ascii_lowercase();
+            SUPPORTED_EXTENSIONS.contains(&lower.as_str())
+        })
+}
+
+/// Collects the source paths of every per-file error in `events`, in the
+/// order they were reported.
+pub fn failed_paths(events: &[LogEvent]) -> Vec<PathBuf> {
+    events
+        .iter()
+        .filter_map(|event| match event {
+            LogEvent::Error { path: Some(path), .. } => Some(path.clone()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Re-runs the conversion for `failed` only, typically the result of
+/// [`failed_paths`] on the previous run's events.
+pub fn retry_failed<F>(
+    failed: &[PathBuf],
+    output_dir: &str,
+    options: &ConvertOptions,
+    log_fn: F,
+) -> anyhow::Result<()>
+where
+    F: Fn(LogEvent) + Sync + Send,
+{
+    ensure!(!failed.is_empty(), "No failed files to retry.");
+    convert_files_to_webp_threads(failed.to_vec(), output_dir, options, log_fn)
+}
+
+/// Continues a run from the journal it left behind. Finished sources are
+/// not touched again, and temp files the interrupted run left are deleted;
+/// outputs are only ever moved into place whole, so existing ones are kept.
+/// A directory whose scan never finished is scanned again, so sources it had
+/// not reached yet are converted too.
+pub fn resume_run<F>(
+    state: &JournalState,
+    output_dir: &str,
+    options: &ConvertOptions,
+    log_fn: F,
+) -> anyhow::Result<()>
+where
+    F: Fn(LogEvent) + Sync + Send,
+{
+    let remaining = state.remaining();
+    let rescan = state.unfinished_scan();
+    ensure!(
+        !remaining.is_empty() || rescan.is_some(),
+        "Nothing left to resume."
+    );
+    let output_dirs: HashSet<PathBuf> = remaining
+        .iter()
+        .filter_map(|path| {
+            let output = options.output_path(path, Path::new(output_dir));
+            output.parent().map(Path::to_path_buf)
         })
         .collect();
+    for dir in &output_dirs {
+        remove_stale_temps(dir);
+    }
+    match rescan {
+        Some(dir) => {
+            let dir = dir.to_string_lossy();
+            convert_dir(&dir, output_dir, options, &state.completed, log_fn)
+        }
+        None => convert_files_to_webp_threads(remaining, output_dir, options, log_fn),
+    }
+}
 
-    log_fn(LogEvent::Discovered { total: entries.len() });
+fn describe_sources(paths: &[PathBuf]) -> String {
+    let first = paths.first().and_then(|p| p.parent());
+    if let Some(dir) = first
+        && paths.iter().all(|p| p.parent() == Some(dir))
+    {
+        return dir.display().to_string();
+    }
+    format!("{} files", paths.len())
+}
 
-    let success_count = AtomicUsize::new(0);
-    let skipped_count = AtomicUsize::new(0);
-    let error_count = AtomicUsize::new(0);
+/// Maps a source image to `<output_dir>/<stem>.webp`.
+fn output_path_for(path: &Path, output_dir: &Path) -> PathBuf {
+    let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("image");
+    output_dir.join(format!("{stem}.webp"))
+}
 
-    entries.par_iter().for_each(|path| {
-        let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("image");
-        let output_path = output_dir.join(format!("{stem}.webp"));
+/// Applies `action` to `source` once `output` has been read back and
+/// decoded. Returns where the source was moved, if anywhere.
+fn dispose_source(
+    source: &Path,
+    output: &Path,
+    action: &SourceAction,
+) -> Result<Option<PathBuf>> {
+    let written = fs::read(output).with_context(|| format!("Failed to read back {:?}", output))?;
+    decode_webp(&written).with_context(|| format!("Output does not decode: {:?}", output))?;
 
-        if output_path.exists() {
-            skipped_count.fetch_add(1, Ordering::Relaxed);
-            log_fn(LogEvent::Skipped {
-                path: path.clone(),
-                reason: SkipReason::OutputExists,
-            });
-            return;
+    match action {
+        SourceAction::Keep => Ok(None),
+        SourceAction::Delete => {
+            fs::remove_file(source).with_context(|| format!("Failed to delete {:?}", source))?;
+            Ok(None)
+        }
+        SourceAction::MoveTo(dir) => {
+            let name = source.file_name().context("Source has no file name")?;
+            let target = dir.join(name);
+            fs::create_dir_all(dir)?;
+            move_without_overwrite(source, &target)?;
+            Ok(Some(target))
+        }
+    }
+}
+
+/// Moves `source` to `target`, failing rather than replacing a file that
+/// is already there, even one another worker creates at the same moment.
+fn move_without_overwrite(source: &Path, target: &Path) -> Result<()> {
+    let exists = || anyhow!("Archive already contains {:?}", target);
+    match fs::hard_link(source, target) {
+        Ok(()) => {}
+        Err(e) if e.kind() == io::ErrorKind::AlreadyExists => return Err(exists()),
+        // Another filesystem, or one without hard links: copy instead.
+        Err(_) => {
+            let mut from =
+                File::open(source).with_context(|| format!("Failed to open {:?}", source))?;
+            let mut to = match File::create_new(target) {
+                Ok(file) => file,
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => return Err(exists()),
+                Err(e) => return Err(e).with_context(|| format!("Failed to create {:?}", target)),
+            };
+            let copied = io::copy(&mut from, &mut to)
+                .and_then(|_| to.set_permissions(from.metadata()?.permissions()));
+            if let Err(e) = copied {
+                let _ = fs::remove_file(target);
+                return Err(e)
+                    .with_context(|| format!("Failed to move {:?} to {:?}", source, target));
+            }
+        }
+    }
+    fs::remove_file(source).with_context(|| format!("Failed to remove {:?}", source))
+}
+
+/// Per-run counters shared by the worker threads.
+pub(crate) struct Tally {
+    success: AtomicUsize,
+    skipped: AtomicUsize,
+    errors: AtomicUsize,
+    bytes_read: AtomicU64,
+    started: Instant,
+    budget: Option<MemoryBudget>,
+    journal: Option<Arc<Journal>>,
+    /// Outputs written during the run, mapped to their source.
+    claimed: Mutex<HashMap<PathBuf, PathBuf>>,
+}
+
+impl Tally {
+    pub(crate) fn new(options: &ConvertOptions) -> Self {
+        Self {
+            success: AtomicUsize::new(0),
+            skipped: AtomicUsize::new(0),
+            errors: AtomicUsize::new(0),
+            bytes_read: AtomicU64::new(0),
+            started: Instant::now(),
+            budget: options.memory_budget.map(MemoryBudget::new),
+            journal: options.journal.clone(),
+            claimed: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Reserves `output_path` for `path`, or returns the source that
+    /// already holds it.
+    fn claim(&self, path: &Path, output_path: &Path) -> Option<PathBuf> {
+        let mut claimed = self.claimed.lock().unwrap_or_else(|e| e.into_inner());
+        match claimed.get(output_path) {
+            Some(by) if by != path => Some(by.clone()),
+            Some(_) => None,
+            None => {
+                claimed.insert(output_path.to_path_buf(), path.to_path_buf());
+                None
+            }
         }
+    }
+
+    pub(crate) fn skip<F>(&self, path: &Path, reason: SkipReason, log_fn: &F)
+    where
+        F: Fn(LogEvent),
+    {
+        self.skipped.fetch_add(1, Ordering::Relaxed);
+        if let Some(journal) = &self.journal {
+            journal.completed(path);
+        }
+        log_fn(LogEvent::Skipped {
+            path: path.to_path_buf(),
+            reason,
+        });
+    }
 
-        match convert_to_webp(path, &output_path, quality, lossless) {
-            Ok(_) => {
-                success_count.fetch_add(1, Ordering::Relaxed);
-                log_fn(LogEvent::Converted { path: path.clone() });
+    /// Converts `path` into `output_path` and logs the outcome.
+    pub(crate) fn convert<F>(
+        &self,
+        probe: &Probe,
+        output_path: &Path,
+        options: &ConvertOptions,
+        log_fn: &F,
+    ) where
+        F: Fn(LogEvent),
+    {
+        let path = probe.path;
+        if let Some(by) = self.claim(path, output_path) {
+            self.skip(path, SkipReason::OutputClaimed { by }, log_fn);
+            return;
+        }
+        let plan = options.plan_decode(probe);
+        // Files whose header can't be read cost nothing and fail on decode.
+        let _reservation = self
+            .budget
+            .as_ref()
+            .map(|budget| budget.reserve(options.decode_bytes(probe, &plan)));
+        match convert_planned(path, output_path, options, plan) {
+            Ok(FileOutcome::Converted(result)) => {
+                self.success.fetch_add(1, Ordering::Relaxed);
+                self.bytes_read.fetch_add(result.source_len, Ordering::Relaxed);
+                if let Some(journal) = &self.journal {
+                    journal.completed(path);
+                }
+                log_fn(LogEvent::Converted {
+                    path: path.to_path_buf(),
+                    result,
+                });
+                if options.source_action == SourceAction::Keep {
+                    return;
+                }
+                match dispose_source(path, output_path, &options.source_action) {
+                    Ok(moved_to) => log_fn(LogEvent::SourceRemoved {
+                        path: path.to_path_buf(),
+                        moved_to,
+                    }),
+                    // The conversion itself succeeded, so this is not a per-file error.
+                    Err(e) => log_fn(LogEvent::Error {
+                        path: None,
+                        msg: format!("Kept {}: {e:#}", path.display()),
+                    }),
+                }
+            }
+            Ok(FileOutcome::Skipped(reason)) => {
+                if let SkipReason::NotSmaller { source_len, .. } = reason {
+                    self.bytes_read.fetch_add(source_len, Ordering::Relaxed);
+                }
+                self.skip(path, reason, log_fn);
             }
             Err(e) => {
-                error_count.fetch_add(1, Ordering::Relaxed);
+                self.errors.fetch_add(1, Ordering::Relaxed);
                 log_fn(LogEvent::Error {
-                    msg: format!("{}: {}", path.display(), e),
+                    path: Some(path.to_path_buf()),
+                    msg: format!("{e:#}"),
                 });
             }
         }
+    }
+
+    pub(crate) fn finished(&self, total: usize) -> LogEvent {
+        LogEvent::Finished {
+            success: self.success.load(Ordering::Relaxed),
+            skipped: self.skipped.load(Ordering::Relaxed),
+            errors: self.errors.load(Ordering::Relaxed),
+            total,
+            elapsed: self.started.elapsed(),
+            bytes_read: self.bytes_read.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Converts a known list of `entries`.
+fn convert_entries<F>(
+    entries: &[PathBuf],
+    output_dir: &Path,
+    options: &ConvertOptions,
+    log_fn: &F,
+) -> Result<()>
+where
+    F: Fn(LogEvent) + Sync + Send,
+{
+    let total = entries.len();
+    log_fn(LogEvent::Discovered {
+        found: total,
+        total,
+        complete: true,
     });
 
-    log_fn(LogEvent::Finished {
-        success: success_count.load(Ordering::Relaxed),
-        skipped: skipped_count.load(Ordering::Relaxed),
-        errors: error_count.load(Ordering::Relaxed),
-        total: entries.len(),
+    if let Some(journal) = &options.journal {
+        journal.discovered(entries);
+    }
+    let inputs: HashSet<&Path> = entries.iter().map(PathBuf::as_path).collect();
+    let tally = Tally::new(options);
+
+    let pool = options.thread_pool()?;
+    pool.install(|| {
+        entries.par_iter().for_each(|path| {
+            options.control.checkpoint(log_fn);
+            let output_path = options.output_path(path, output_dir);
+
+            // Never write over a file that is itself being converted.
+            if inputs.contains(output_path.as_path()) {
+                tally.skip(path, SkipReason::OutputIsInput, log_fn);
+                return;
+            }
+            convert_unless_exists(&Probe::new(path), &output_path, options, &tally, log_fn);
+        });
     });
 
+    log_fn(tally.finished(total));
     Ok(())
 }
 
+/// Batch runs never replace an existing output.
+fn convert_unless_exists<F>(
+    probe: &Probe,
+    output_path: &Path,
+    options: &ConvertOptions,
+    tally: &Tally,
+    log_fn: &F,
+) where
+    F: Fn(LogEvent),
+{
+    if output_path.exists() {
+        tally.skip(probe.path, SkipReason::OutputExists, log_fn);
+    } else {
+        tally.convert(probe, output_path, options, log_fn);
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    /// An empty directory of its own for one test, removed when dropped, so
+    /// tests never share files with each other or with concurrent runs.
+    struct ScratchDir(PathBuf);
+
+    impl ScratchDir {
+        fn new(name: &str) -> Self {
+            static NEXT: AtomicUsize = AtomicUsize::new(0);
+            let n = NEXT.fetch_add(1, Ordering::Relaxed);
+            let dir = std::env::temp_dir().join(format!("webp_{name}_{}_{n}", std::process::id()));
+            let _ = fs::remove_dir_all(&dir);
+            fs::create_dir_all(&dir).unwrap();
+            Self(dir)
+        }
+    }
+
+    impl AsRef<Path> for ScratchDir {
+        fn as_ref(&self) -> &Path {
+            &self.0
+        }
+    }
+
+    impl std::ops::Deref for ScratchDir {
+        type Target = Path;
+
+        fn deref(&self) -> &Path {
+            &self.0
+        }
+    }
+
+    impl Drop for ScratchDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    /// Writes a small 24-bit BMP gradient; much faster to convert in debug
+    /// builds than the fixture photos.
+    fn write_test_bmp(path: &Path) -> &Path {
+        let (w, h) = (24u32, 16u32);
+        let row = (w * 3).div_ceil(4) * 4;
+        let size = 54 + row * h;
+        let mut bmp = b"BM".to_vec();
+        for v in [size, 0, 54, 40, w, h] {
+            bmp.extend(v.to_le_bytes());
+        }
+        bmp.extend(1u16.to_le_bytes());
+        bmp.extend(24u16.to_le_bytes());
+        bmp.extend([0u8; 24]);
+        for y in 0..h {
+            for x in 0..w {
+                bmp.extend([(x * 10) as u8, (y * 15) as u8, ((x + y) * 6) as u8]);
+            }
+            bmp.resize(bmp.len() + (row - w * 3) as usize, 0);
+        }
+        fs::write(path, bmp).unwrap();
+        path
+    }
+
     #[test]
     fn convert_to_webp_rejects_same_path() {
         let p = Path::new("a.webp");
@@ -177,9 +1170,640 @@ mod tests {
 
     #[test]
     fn convert_dir_rejects_empty_paths() {
-        let r = convert_to_webp_dir_threads("", "out", 87.0, false, |_| {});
+        let r = convert_to_webp_dir_threads("", "out", &ConvertOptions::default(), |_| {});
+        assert!(r.is_err());
+        let r = convert_to_webp_dir_threads("in", "", &ConvertOptions::default(), |_| {});
+        assert!(r.is_err());
+    }
+
+    #[test]
+    fn convert_files_rejects_empty_list() {
+        let options = ConvertOptions::default();
+        let r = convert_files_to_webp_threads(Vec::new(), "out", &options, |_| {});
+        assert!(r.is_err());
+    }
+
+    #[test]
+    fn is_supported_image_ignores_case() {
+        assert!(is_supported_image(Path::new("a/B.PNG")));
+        assert!(is_supported_image(Path::new("c.jpeg")));
+        assert!(!is_supported_image(Path::new("d.webp")));
+        assert!(!is_supported_image(Path::new("noext")));
+    }
+
+    #[test]
+    fn watch_rejects_missing_input_dir() {
+        let stop = std::sync::atomic::AtomicBool::new(true);
+        let options = ConvertOptions::default();
+        let r = watch_dir_threads("does/not/exist", "out", &options, &stop, |_| {});
+        assert!(r.is_err());
+    }
+
+    #[test]
+    fn filtered_files_are_reported_as_skipped() {
+        let input = concat!(env!("CARGO_MANIFEST_DIR"), "/test/input");
+        let output = ScratchDir::new("filter");
+        let options = ConvertOptions {
+            filter: DiscoveryFilter {
+                exclude: vec!["bad.*".into()],
+                min_width: Some(u32::MAX),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let events = std::sync::Mutex::new(Vec::new());
+        convert_to_webp_dir_threads(input, output.to_str().unwrap(), &options, |e| {
+            events.lock().unwrap().push(e)
+        })
+        .unwrap();
+
+        let mut rules: Vec<String> = events
+            .into_inner()
+            .unwrap()
+            .into_iter()
+            .filter_map(|e| match e {
+                LogEvent::Skipped {
+                    reason: SkipReason::Filtered { rule },
+                    ..
+                } => Some(rule),
+                _ => None,
+            })
+            .collect();
+        rules.sort();
+        assert_eq!(rules, ["exclude pattern `bad.*`", "narrower than 4294967295 px"]);
+    }
+
+    #[test]
+    fn runs_that_fail_setup_never_report_a_start() {
+        let dir = ScratchDir::new("setup");
+        let events = std::sync::Mutex::new(Vec::new());
+        let missing = dir.join("missing");
+        let output = dir.join("out");
+        let r = convert_to_webp_dir_threads(
+            missing.to_str().unwrap(),
+            output.to_str().unwrap(),
+            &ConvertOptions::default(),
+            |e| events.lock().unwrap().push(e),
+        );
+        assert!(r.is_err());
+        assert!(events.into_inner().unwrap().is_empty());
+    }
+
+    #[test]
+    fn invalid_glob_is_an_error() {
+        let options = ConvertOptions {
+            filter: DiscoveryFilter {
+                include: vec!["[".into()],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let input = concat!(env!("CARGO_MANIFEST_DIR"), "/test/input");
+        let output = ScratchDir::new("glob");
+        let r = convert_to_webp_dir_threads(input, output.to_str().unwrap(), &options, |_| {});
         assert!(r.is_err());
-        let r = convert_to_webp_dir_threads("in", "", 87.0, false, |_| {});
+    }
+
+    #[test]
+    fn image_dimensions_reads_header() {
+        let png = Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/test/input/good.png"));
+        let (w, h) = image_dimensions(png).unwrap();
+        assert!(w > 0 && h > 0);
+    }
+
+    #[test]
+    fn webp_round_trip_keeps_dimensions() {
+        let png = Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/test/input/good.png"));
+        let image = load_rgba(png).unwrap();
+        for lossless in [false, true] {
+            let decoded = decode_webp(&encode_webp(&image, 75.0, lossless)).unwrap();
+            assert_eq!((decoded.width, decoded.height), (image.width, image.height));
+            assert_eq!(decoded.pixels.len(), image.pixels.len());
+        }
+    }
+
+    #[test]
+    fn identical_images_score_perfectly() {
+        let dir = ScratchDir::new("metrics");
+        let bmp = write_test_bmp(&dir.join("a.bmp")).to_path_buf();
+        let image = load_rgba(&bmp).unwrap();
+        let scores = compare_images(&image, &image).unwrap();
+        assert!(scores.psnr.is_infinite());
+        assert!((scores.ssim - 1.0).abs() < 1e-9);
+
+        let mut shifted = image.clone();
+        shifted.pixels.iter_mut().for_each(|v| *v = v.wrapping_add(64));
+        let scores = compare_images(&image, &shifted).unwrap();
+        assert!(scores.psnr.is_finite() && scores.ssim < 1.0);
+    }
+
+    #[test]
+    fn unmet_min_ssim_raises_quality_then_flags() {
+        let dir = ScratchDir::new("min_ssim");
+        let bmp = write_test_bmp(&dir.join("a.bmp")).to_path_buf();
+        let output = dir.join("a.webp");
+        let options = ConvertOptions {
+            quality: 50.0,
+            min_ssim: Some(1.5),
+            raise_quality: true,
+            ..Default::default()
+        };
+        let Ok(FileOutcome::Converted(result)) = convert_file(&bmp, &output, &options) else {
+            panic!("expected a conversion");
+        };
+        assert_eq!(result.quality, 100.0);
+        assert!(result.scores.is_some());
+        assert!(result.below_min_ssim);
+    }
+
+    #[test]
+    fn auto_lossless_keeps_the_smaller_output() {
+        let dir = ScratchDir::new("auto");
+        let bmp = write_test_bmp(&dir.join("a.bmp")).to_path_buf();
+        let image = load_rgba(&bmp).unwrap();
+        let lossless_len = encode_webp(&image, 87.0, true).len();
+        let lossy_len = encode_webp(&image, 87.0, false).len();
+
+        let output = dir.join("a.webp");
+        let options = ConvertOptions {
+            auto_lossless: true,
+            ..Default::default()
+        };
+        let Ok(FileOutcome::Converted(result)) = convert_file(&bmp, &output, &options) else {
+            panic!("expected a conversion");
+        };
+        assert_eq!(result.lossless, lossless_len <= lossy_len);
+        let written = fs::metadata(&output).unwrap().len() as usize;
+        assert_eq!(written, lossless_len.min(lossy_len));
+    }
+
+    #[test]
+    fn output_without_enough_savings_is_replaced_by_original() {
+        let source = ScratchDir::new("not_smaller_source");
+        let bmp = write_test_bmp(&source.join("a.bmp")).to_path_buf();
+        let dir = ScratchDir::new("not_smaller");
+        let output = dir.join("a.webp");
+        let options = ConvertOptions {
+            lossless: true,
+            min_savings: Some(100.0),
+            copy_original: true,
+            ..Default::default()
+        };
+        let outcome = convert_file(&bmp, &output, &options).unwrap();
+        assert!(matches!(
+            outcome,
+            FileOutcome::Skipped(SkipReason::NotSmaller { original: OriginalCopy::Copied, .. })
+        ));
+        assert!(!output.exists());
+        assert!(dir.join("a.bmp").exists());
+
+        // An unrelated file of the same name is never overwritten.
+        fs::write(dir.join("a.bmp"), "other").unwrap();
+        let outcome = convert_file(&bmp, &output, &options).unwrap();
+        assert!(matches!(
+            outcome,
+            FileOutcome::Skipped(SkipReason::NotSmaller { original: OriginalCopy::NameTaken, .. })
+        ));
+        assert_eq!(fs::read(dir.join("a.bmp")).unwrap(), b"other");
+    }
+
+    #[test]
+    fn sources_are_archived_after_conversion() {
+        let dir = ScratchDir::new("archive");
+        let archive = dir.join("archive");
+        let source = write_test_bmp(&dir.join("a.bmp")).to_path_buf();
+        let options = ConvertOptions {
+            source_action: SourceAction::MoveTo(archive.clone()),
+            ..Default::default()
+        };
+        let events = std::sync::Mutex::new(Vec::new());
+        let output = dir.join("out");
+        let files = vec![source.clone()];
+        convert_files_to_webp_threads(files, output.to_str().unwrap(), &options, |e| {
+            events.lock().unwrap().push(e)
+        })
+        .unwrap();
+
+        assert!(!source.exists());
+        assert!(archive.join("a.bmp").exists());
+        assert!(output.join("a.webp").exists());
+        let removed = events
+            .into_inner()
+            .unwrap()
+            .into_iter()
+            .any(|e| matches!(e, LogEvent::SourceRemoved { moved_to: Some(_), .. }));
+        assert!(removed);
+
+        // An archived original of the same name is never replaced.
+        let other = dir.join("other");
+        fs::create_dir_all(&other).unwrap();
+        let source = write_test_bmp(&other.join("a.bmp")).to_path_buf();
+        let webp = other.join("a.webp");
+        fs::write(&webp, encode_webp(&load_rgba(&source).unwrap(), 80.0, false)).unwrap();
+        let archived = fs::read(archive.join("a.bmp")).unwrap();
+        fs::write(&source, b"second").unwrap();
+        let r = dispose_source(&source, &webp, &options.source_action);
+        assert!(r.unwrap_err().to_string().contains("Archive already contains"));
+        assert_eq!(fs::read(archive.join("a.bmp")).unwrap(), archived);
+        assert_eq!(fs::read(&source).unwrap(), b"second");
+    }
+
+    #[test]
+    fn in_place_writes_beside_sources() {
+        let dir = ScratchDir::new("in_place");
+        let source = write_test_bmp(&dir.join("a.bmp")).to_path_buf();
+        let options = ConvertOptions {
+            in_place: true,
+            ..Default::default()
+        };
+        convert_to_webp_dir_threads(dir.to_str().unwrap(), "", &options, |_| {}).unwrap();
+
+        assert!(source.exists());
+        assert!(dir.join("a.webp").exists());
+    }
+
+    #[test]
+    fn invalid_output_is_never_moved_into_place() {
+        let dir = ScratchDir::new("atomic");
+        let output = dir.join("a.webp");
+
+        assert!(write_webp_atomically(&output, b"not a webp").is_err());
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 0);
+
+        let source = dir.join("a.bmp");
+        let webp = encode_webp(&load_rgba(write_test_bmp(&source)).unwrap(), 80.0, false);
+        write_webp_atomically(&output, &webp).unwrap();
+        assert_eq!(fs::read(&output).unwrap(), webp);
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 2);
+    }
+
+    #[test]
+    fn sources_sharing_an_output_convert_once() {
+        let dir = ScratchDir::new("claim");
+        let sources: Vec<PathBuf> = (0..8)
+            .map(|i| {
+                let sub = dir.join(i.to_string());
+                fs::create_dir_all(&sub).unwrap();
+                write_test_bmp(&sub.join("a.bmp")).to_path_buf()
+            })
+            .collect();
+        let options = ConvertOptions {
+            workers: Some(4),
+            ..Default::default()
+        };
+        let events = std::sync::Mutex::new(Vec::new());
+        let output = dir.join("out");
+        convert_files_to_webp_threads(sources, output.to_str().unwrap(), &options, |e| {
+            events.lock().unwrap().push(e)
+        })
+        .unwrap();
+
+        let events = events.into_inner().unwrap();
+        assert!(matches!(
+            events.last(),
+            Some(LogEvent::Finished { success: 1, skipped: 7, errors: 0, .. })
+        ));
+        assert_eq!(fs::read_dir(&output).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn source_times_are_copied_to_output() {
+        let dir = ScratchDir::new("times");
+        let source = write_test_bmp(&dir.join("a.bmp")).to_path_buf();
+        let past = std::time::UNIX_EPOCH + std::time::Duration::from_secs(1_000_000_000);
+        let times = FileTimes::new().set_modified(past).set_accessed(past);
+        File::options().write(true).open(&source).unwrap().set_times(times).unwrap();
+
+        let output = dir.join("a.webp");
+        let options = ConvertOptions {
+            preserve_times: true,
+            preserve_permissions: true,
+            ..Default::default()
+        };
+        convert_file(&source, &output, &options).unwrap();
+        let meta = fs::metadata(&output).unwrap();
+        assert_eq!(meta.modified().unwrap(), past);
+        // Not the time the conversion itself read the source.
+        assert_eq!(meta.accessed().unwrap(), past);
+    }
+
+    #[test]
+    fn exif_date_time_original_is_read() {
+        // TIFF header, IFD0 with one Exif-IFD pointer, Exif IFD with the date.
+        let mut tiff = b"II*\0\x08\0\0\0".to_vec();
+        tiff.extend([1, 0, 0x69, 0x87, 4, 0, 1, 0, 0, 0, 26, 0, 0, 0, 0, 0, 0, 0]);
+        tiff.extend([1, 0, 0x03, 0x90, 2, 0, 20, 0, 0, 0, 44, 0, 0, 0, 0, 0, 0, 0]);
+        tiff.extend(b"2001:09:09 01:46:40\0");
+        // SOI, then a JFIF APP0 segment to skip, then the EXIF APP1 segment.
+        let mut jpeg = vec![0xFF, 0xD8, 0xFF, 0xE0, 0, 16];
+        jpeg.extend(b"JFIF\0\x01\x01\0\0\x01\0\x01\0\0");
+        jpeg.extend([0xFF, 0xE1]);
+        jpeg.extend(((tiff.len() + 8) as u16).to_be_bytes());
+        jpeg.extend(b"Exif\0\0");
+        jpeg.extend(tiff);
+        jpeg.extend([0xFF, 0xDA]);
+
+        let dir = ScratchDir::new("exif");
+        fs::write(dir.join("a.jpg"), &jpeg).unwrap();
+        // Only JPEGs are read.
+        fs::write(dir.join("a.png"), &jpeg).unwrap();
+
+        let taken = exif::date_time_original(&dir.join("a.jpg")).unwrap();
+        let expected = std::time::UNIX_EPOCH + std::time::Duration::from_secs(1_000_000_000);
+        assert_eq!(taken, expected);
+        assert!(exif::date_time_original(&dir.join("a.png")).is_none());
+    }
+
+    #[test]
+    fn worker_count_sizes_the_pool() {
+        let options = ConvertOptions {
+            workers: Some(2),
+            ..Default::default()
+        };
+        assert_eq!(options.thread_pool().unwrap().current_num_threads(), 2);
+    }
+
+    #[test]
+    fn presets_round_trip_through_toml() {
+        let options = ConvertOptions {
+            quality: 70.0,
+            min_savings: Some(0.1),
+            max_pixels: Some(40_000_000),
+            ..Default::default()
+        };
+        let preset = Preset::from_options("Thumbnails", &options);
+        let text = preset.to_toml().unwrap();
+        assert!(text.contains("min_savings = 0.1\n"), "{text}");
+        assert_eq!(Preset::from_toml(&text).unwrap(), preset);
+
+        let mut applied = ConvertOptions {
+            in_place: true,
+            ..Default::default()
+        };
+        Preset::from_toml("name = \"Icons\"\nquality = 100\nlossless = true\n")
+            .unwrap()
+            .apply(&mut applied);
+        assert!(applied.lossless && applied.in_place);
+        assert_eq!(applied.quality, 100.0);
+        assert!(Preset::from_toml("name = \"x\"\nquality = \"high\"").is_err());
+    }
+
+    #[test]
+    fn oversized_sources_are_skipped_or_downscaled() {
+        let dir = ScratchDir::new("too_large");
+        let bmp = dir.join("big.bmp");
+        let options = ConvertOptions {
+            max_pixels: Some(100),
+            downscale_large: true,
+            ..Default::default()
+        };
+        // Only JPEGs are downscaled; anything else over the limit is skipped.
+        let outcome = convert_file(write_test_bmp(&bmp), &dir.join("big.webp"), &options);
+        assert!(matches!(
+            outcome.unwrap(),
+            FileOutcome::Skipped(SkipReason::TooLarge { width: 24, height: 16 })
+        ));
+
+        let options = ConvertOptions {
+            max_pixels: Some(10_000),
+            ..options
+        };
+        let jpeg = Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/examples/preview.jpg"));
+        let output = dir.join("preview.webp");
+        convert_file(jpeg, &output, &options).unwrap();
+        let image = decode_webp(&fs::read(&output).unwrap()).unwrap();
+        assert!(u64::from(image.width) * u64::from(image.height) <= 10_000);
+        let (w, h) = image_dimensions(jpeg).unwrap();
+        assert!(image.width < w && image.height < h);
+    }
+
+    #[test]
+    fn downscaled_jpegs_are_budgeted_at_their_scaled_size() {
+        let header = Header {
+            width: 8000,
+            height: 6000,
+            progressive: false,
+        };
+        let full = 8000 * 6000 * 4;
+        // 1/4 scale reaches 2000×1500 directly, leaving a 2× box filter.
+        let baseline = downscale::peak_bytes(header, 1_000_000);
+        assert_eq!(baseline, 2000 * 1500 * 3 + 1_000_000 * 4);
+        let progressive = Header {
+            progressive: true,
+            ..header
+        };
+        assert!(baseline < full && downscale::peak_bytes(progressive, 1_000_000) > full);
+    }
+
+    #[test]
+    fn memory_budget_admits_oversized_image_when_idle() {
+        let budget = MemoryBudget::new(100);
+        let first = budget.reserve(1_000);
+        drop(first);
+        let small = budget.reserve(60);
+        let released = std::sync::atomic::AtomicBool::new(false);
+        std::thread::scope(|scope| {
+            scope.spawn(|| {
+                let _waiting = budget.reserve(60);
+                assert!(released.load(Ordering::SeqCst));
+            });
+            std::thread::sleep(std::time::Duration::from_millis(50));
+            released.store(true, Ordering::SeqCst);
+            drop(small);
+        });
+    }
+
+    #[test]
+    fn paused_run_logs_pause_once_and_resumes() {
+        let control = RunControl::default();
+        control.pause();
+        let events = std::sync::Mutex::new(Vec::new());
+        let log_fn = |e| events.lock().unwrap().push(e);
+        std::thread::scope(|scope| {
+            for _ in 0..3 {
+                scope.spawn(|| control.checkpoint(&log_fn));
+            }
+            std::thread::sleep(std::time::Duration::from_millis(50));
+            assert!(control.is_paused());
+            control.resume();
+        });
+        let events = events.into_inner().unwrap();
+        assert_eq!(events.len(), 2);
+        assert!(matches!(events[0], LogEvent::Paused));
+        assert!(matches!(events[1], LogEvent::Resumed));
+    }
+
+    #[test]
+    fn resume_skips_completed_and_removes_leftover_temps() {
+        let dir = ScratchDir::new("resume");
+        let output = dir.join("out");
+        fs::create_dir_all(&output).unwrap();
+        let sources: Vec<PathBuf> = ["a.bmp", "b.bmp", "c.bmp"]
+            .iter()
+            .map(|name| write_test_bmp(&dir.join(name)).to_path_buf())
+            .collect();
+
+        let journal_path = dir.join("run.journal");
+        let journal = Journal::create(&journal_path, "my settings").unwrap();
+        journal.discovered(&sources);
+        journal.completed(&sources[0]);
+        drop(journal);
+        // A temp file the crash left behind, and a WebP the user made.
+        fs::write(output.join(".b.webp.4321.7.tmp"), b"truncated").unwrap();
+        fs::write(output.join("c.webp"), b"animated").unwrap();
+
+        let state = JournalState::load(&journal_path).unwrap().unwrap();
+        assert_eq!(state.settings, "my settings");
+        assert_eq!(state.remaining(), sources[1..]);
+
+        let events = std::sync::Mutex::new(Vec::new());
+        let options = ConvertOptions::default();
+        resume_run(&state, output.to_str().unwrap(), &options, |e| {
+            events.lock().unwrap().push(e)
+        })
+        .unwrap();
+        let events = events.into_inner().unwrap();
+        assert!(matches!(
+            events.last(),
+            Some(LogEvent::Finished { success: 1, skipped: 1, errors: 0, .. })
+        ));
+        assert!(!output.join("a.webp").exists());
+        assert_eq!(fs::read(output.join("c.webp")).unwrap(), b"animated");
+        assert!(!output.join(".b.webp.4321.7.tmp").exists());
+    }
+
+    #[test]
+    fn journal_values_may_span_lines() {
+        let dir = ScratchDir::new("journal");
+        let path = dir.join("run.journal");
+        let settings = "input = \"C:\\in\"\nfiles = []\n";
+        let sources = [PathBuf::from("line\nbreak.png"), PathBuf::from("tab\tand\\n.png")];
+        let journal = Journal::create(&path, settings).unwrap();
+        journal.discovered(&sources);
+        journal.completed(&sources[0]);
+        drop(journal);
+
+        let state = JournalState::load(&path).unwrap().unwrap();
+        assert_eq!(state.settings, settings);
+        assert_eq!(state.discovered, sources);
+        assert_eq!(state.remaining(), sources[1..]);
+
+        // Journals from before escaping are read verbatim.
+        fs::write(&path, "settings\tC:\\new\nfile\tC:\\new\\a.png\n").unwrap();
+        let state = JournalState::load(&path).unwrap().unwrap();
+        assert_eq!(state.settings, "C:\\new");
+        assert_eq!(state.discovered, [PathBuf::from("C:\\new\\a.png")]);
+    }
+
+    #[test]
+    fn resume_rescans_a_directory_whose_scan_never_finished() {
+        let dir = ScratchDir::new("rescan");
+        let input = dir.join("in");
+        fs::create_dir_all(&input).unwrap();
+        let sources: Vec<PathBuf> = ["a.bmp", "b.bmp", "c.bmp"]
+            .iter()
+            .map(|name| write_test_bmp(&input.join(name)).to_path_buf())
+            .collect();
+
+        // Crashed after converting `a`, before the scan reached the rest.
+        let journal_path = dir.join("run.journal");
+        let journal = Journal::create(&journal_path, "").unwrap();
+        journal.scan_started(&input);
+        journal.discovered(&sources[..1]);
+        journal.completed(&sources[0]);
+        drop(journal);
+
+        let state = JournalState::load(&journal_path).unwrap().unwrap();
+        assert!(state.remaining().is_empty());
+        assert_eq!(state.unfinished_scan(), Some(input.as_path()));
+
+        let events = std::sync::Mutex::new(Vec::new());
+        let output = dir.join("out");
+        let options = ConvertOptions::default();
+        resume_run(&state, output.to_str().unwrap(), &options, |e| {
+            events.lock().unwrap().push(e)
+        })
+        .unwrap();
+        let events = events.into_inner().unwrap();
+        assert!(matches!(
+            events.last(),
+            Some(LogEvent::Finished { success: 2, total: 2, .. })
+        ));
+        assert!(!output.join("a.webp").exists());
+
+        // A finished scan is not repeated.
+        let journal = Journal::create(&journal_path, "").unwrap();
+        journal.scan_started(&input);
+        journal.scan_finished();
+        drop(journal);
+        let state = JournalState::load(&journal_path).unwrap().unwrap();
+        assert_eq!(state.unfinished_scan(), None);
+    }
+
+    #[test]
+    fn discovery_streams_and_reports_completion_once() {
+        let dir = ScratchDir::new("discovery");
+        for name in ["a.bmp", "b.bmp", "c.bmp"] {
+            write_test_bmp(&dir.join(name));
+        }
+        fs::write(dir.join("notes.txt"), "not an image").unwrap();
+
+        let events = std::sync::Mutex::new(Vec::new());
+        let output = dir.join("out");
+        let options = ConvertOptions::default();
+        convert_to_webp_dir_threads(dir.to_str().unwrap(), output.to_str().unwrap(), &options, |e| {
+            events.lock().unwrap().push(e)
+        })
+        .unwrap();
+
+        let events = events.into_inner().unwrap();
+        let discovered: Vec<_> = events
+            .iter()
+            .filter_map(|e| match e {
+                LogEvent::Discovered {
+                    found,
+                    total,
+                    complete,
+                } => Some((*found, *total, *complete)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(discovered, [(3, 3, true)]);
+        // Three identical sources, all read once.
+        let source_len = fs::metadata(dir.join("a.bmp")).unwrap().len();
+        assert!(matches!(
+            events.last(),
+            Some(LogEvent::Finished {
+                success: 3,
+                total: 3,
+                bytes_read,
+                ..
+            }) if *bytes_read == 3 * source_len
+        ));
+    }
+
+    #[test]
+    fn failed_paths_collects_per_file_errors_only() {
+        let events = vec![
+            LogEvent::Skipped {
+                path: PathBuf::from("a.png"),
+                reason: SkipReason::OutputExists,
+            },
+            LogEvent::Error {
+                path: Some(PathBuf::from("b.png")),
+                msg: "boom".into(),
+            },
+            LogEvent::Error {
+                path: None,
+                msg: "Fatal".into(),
+            },
+        ];
+        assert_eq!(failed_paths(&events), vec![PathBuf::from("b.png")]);
+    }
+
+    #[test]
+    fn retry_failed_rejects_empty_list() {
+        let r = retry_failed(&[], "out", &ConvertOptions::default(), |_| {});
         assert!(r.is_err());
     }
 }
@@ -0,0 +1,297 @@
+//! One log file per run on disk, as plain text or JSON lines, pruned to the
+//! most recent few.
+
+use crate::format_event;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+use std::fs::{self, File, OpenOptions};
+use std::io::{LineWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use webp_converter_app::{LogEvent, OriginalCopy, SkipReason};
+
+const PREFIX: &str = "webp-run-";
+
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LogFormat {
+    Text,
+    JsonLines,
+}
+
+impl LogFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            Self::Text => "log",
+            Self::JsonLines => "jsonl",
+        }
+    }
+
+    /// Reads the value of the legacy `log_format` key.
+    pub fn from_storage(value: &str) -> Self {
+        match value {
+            "json" => Self::JsonLines,
+            _ => Self::Text,
+        }
+    }
+}
+
+/// Where runs are logged, and how many logs are kept.
+#[derive(Clone)]
+pub struct LogFileSettings {
+    pub dir: PathBuf,
+    pub format: LogFormat,
+    pub keep: usize,
+}
+
+pub struct LogFile {
+    format: LogFormat,
+    writer: Mutex<LineWriter<File>>,
+}
+
+impl LogFile {
+    /// Starts a timestamped log in `settings.dir` and deletes the oldest
+    /// logs beyond `settings.keep`.
+    pub fn create(settings: &LogFileSettings) -> Result<Self> {
+        fs::create_dir_all(&settings.dir)
+            .with_context(|| format!("Failed to create log folder {:?}", settings.dir))?;
+        let (date, time) = civil_utc(SystemTime::now());
+        let stamp = format!("{}{}", date.replace('-', ""), time.replace([':', '.'], ""));
+        let name = format!("{PREFIX}{stamp}.{}", settings.format.extension());
+        let path = settings.dir.join(name);
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .with_context(|| format!("Failed to create log file {:?}", path))?;
+        prune(&settings.dir, settings.keep);
+        Ok(Self {
+            format: settings.format,
+            writer: Mutex::new(LineWriter::new(file)),
+        })
+    }
+
+    /// Appends `event`. Write errors are ignored so logging never stops a run.
+    pub fn write(&self, event: &LogEvent) {
+        let (date, time) = civil_utc(SystemTime::now());
+        let timestamp = format!("{date}T{time}Z");
+        let line = match self.format {
+            LogFormat::Text => format!("{timestamp}  {}", format_event(event).0),
+            LogFormat::JsonLines => event_json(&timestamp, event),
+        };
+        if let Ok(mut writer) = self.writer.lock() {
+            let _ = writeln!(writer, "{line}");
+        }
+    }
+}
+
+/// Deletes all but the newest `keep` logs; names sort chronologically.
+fn prune(dir: &Path, keep: usize) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    let mut logs: Vec<PathBuf> = entries
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| {
+            p.file_name()
+                .and_then(|n| n.to_str())
+                .is_some_and(|n| n.starts_with(PREFIX))
+        })
+        .collect();
+    logs.sort();
+    let excess = logs.len().saturating_sub(keep.max(1));
+    for old in &logs[..excess] {
+        let _ = fs::remove_file(old);
+    }
+}
+
+fn event_json(timestamp: &str, event: &LogEvent) -> String {
+    let mut json = JsonLine::new();
+    json.str("time", timestamp);
+    match event {
+        LogEvent::Started { input_dir } => {
+            json.str("event", "started");
+            json.str("input_dir", input_dir);
+        }
+        LogEvent::Discovered { total, .. } => {
+            json.str("event", "discovered");
+            json.num("total", total);
+        }
+        LogEvent::Watching { input_dir } => {
+            json.str("event", "watching");
+            json.str("input_dir", input_dir);
+        }
+        LogEvent::Detected { path } => {
+            json.str("event", "detected");
+            json.path("path", Some(path));
+        }
+        LogEvent::Paused => json.str("event", "paused"),
+        LogEvent::Resumed => json.str("event", "resumed"),
+        LogEvent::Converted { path, result } => {
+            json.str("event", "converted");
+            json.path("path", Some(path));
+            json.num("lossless", result.lossless);
+            json.float("quality", f64::from(result.quality));
+            json.num("source_len", result.source_len);
+            json.num("output_len", result.output_len);
+            if let Some(scores) = result.scores {
+                json.float("psnr", scores.psnr);
+                json.float("ssim", scores.ssim);
+            }
+            json.num("below_min_ssim", result.below_min_ssim);
+        }
+        LogEvent::Skipped { path, reason } => {
+            json.str("event", "skipped");
+            json.path("path", Some(path));
+            match reason {
+                SkipReason::OutputExists => json.str("reason", "output_exists"),
+                SkipReason::OutputIsInput => json.str("reason", "output_is_input"),
+                SkipReason::OutputClaimed { by } => {
+                    json.str("reason", "output_claimed");
+                    json.path("by", Some(by));
+                }
+                SkipReason::Filtered { rule } => {
+                    json.str("reason", "filtered");
+                    json.str("rule", rule);
+                }
+                SkipReason::NotSmaller {
+                    source_len,
+                    output_len,
+                    original,
+                } => {
+                    json.str("reason", "not_smaller");
+                    json.num("source_len", source_len);
+                    json.num("output_len", output_len);
+                    json.num("copied", *original == OriginalCopy::Copied);
+                    json.num("name_taken", *original == OriginalCopy::NameTaken);
+                }
+                SkipReason::TooLarge { width, height } => {
+                    json.str("reason", "too_large");
+                    json.num("width", width);
+                    json.num("height", height);
+                }
+            }
+        }
+        LogEvent::SourceRemoved { path, moved_to } => {
+            json.str("event", "source_removed");
+            json.path("path", Some(path));
+            json.path("moved_to", moved_to.as_deref());
+        }
+        LogEvent::Error { path, msg } => {
+            json.str("event", "error");
+            json.path("path", path.as_deref());
+            json.str("message", msg);
+        }
+        LogEvent::Finished {
+            success,
+            skipped,
+            errors,
+            total,
+            elapsed,
+            bytes_read,
+        } => {
+            json.str("event", "finished");
+            json.num("success", success);
+            json.num("skipped", skipped);
+            json.num("errors", errors);
+            json.num("total", total);
+            json.float("elapsed_secs", elapsed.as_secs_f64());
+            json.num("bytes_read", bytes_read);
+        }
+    }
+    json.finish()
+}
+
+/// Builds one flat JSON object.
+struct JsonLine(String);
+
+impl JsonLine {
+    fn new() -> Self {
+        Self(String::from("{"))
+    }
+
+    fn key(&mut self, key: &str) {
+        if self.0.len() > 1 {
+            self.0.push(',');
+        }
+        self.0.push_str(&format!("\"{key}\":"));
+    }
+
+    fn str(&mut self, key: &str, value: &str) {
+        self.key(key);
+        self.0.push('"');
+        for c in value.chars() {
+            match c {
+                '"' => self.0.push_str("\\\""),
+                '\\' => self.0.push_str("\\\\"),
+                '\n' => self.0.push_str("\\n"),
+                '\r' => self.0.push_str("\\r"),
+                '\t' => self.0.push_str("\\t"),
+                c if u32::from(c) < 0x20 => self.0.push_str(&format!("\\u{:04x}", u32::from(c))),
+                c => self.0.push(c),
+            }
+        }
+        self.0.push('"');
+    }
+
+    /// Numbers and booleans, whose `Display` is already valid JSON.
+    fn num(&mut self, key: &str, value: impl Display) {
+        self.key(key);
+        self.0.push_str(&value.to_string());
+    }
+
+    /// JSON has no infinities, as identical images score in PSNR.
+    fn float(&mut self, key: &str, value: f64) {
+        if value.is_finite() {
+            self.num(key, value);
+        } else {
+            self.key(key);
+            self.0.push_str("null");
+        }
+    }
+
+    fn path(&mut self, key: &str, path: Option<&Path>) {
+        match path {
+            Some(path) => self.str(key, &path.display().to_string()),
+            None => {
+                self.key(key);
+                self.0.push_str("null");
+            }
+        }
+    }
+
+    fn finish(mut self) -> String {
+        self.0.push('}');
+        self.0
+    }
+}
+
+/// `YYYY-MM-DD` and `HH:MM:SS.mmm` in UTC.
+fn civil_utc(time: SystemTime) -> (String, String) {
+    let since = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let secs = since.as_secs();
+    let (days, rem) = (secs / 86_400, secs % 86_400);
+    // Howard Hinnant's civil_from_days.
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (
+        format!("{year:04}-{month:02}-{day:02}"),
+        format!(
+            "{:02}:{:02}:{:02}.{:03}",
+            rem / 3600,
+            rem / 60 % 60,
+            rem % 60,
+            since.subsec_millis()
+        ),
+    )
+}
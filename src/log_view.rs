@@ -0,0 +1,271 @@
+//! The run log: search, per-kind toggles and row virtualization, so long
+//! runs stay responsive. Lines are formatted once and filtered again only
+//! when the search or a toggle changes.
+
+use crate::format_event;
+use eframe::egui::{self, Color32, RichText, Sense};
+use std::path::Path;
+use webp_converter_app::LogEvent;
+
+const ROW_HEIGHT: f32 = 16.0;
+
+/// The events of the current run, oldest first.
+#[derive(Default)]
+pub struct RunLog {
+    pub events: Vec<LogEvent>,
+    /// How many events have ever been trimmed or cleared from the front,
+    /// so a view can tell a shifted log from one that only grew.
+    pub dropped: usize,
+}
+
+impl RunLog {
+    pub fn clear(&mut self) {
+        self.dropped += self.events.len();
+        self.events.clear();
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Kind {
+    Converted,
+    Skipped,
+    /// Errors and outputs flagged below the minimum SSIM.
+    Problem,
+    /// Run-level messages, always shown.
+    Info,
+}
+
+fn kind(event: &LogEvent) -> Kind {
+    match event {
+        LogEvent::Error { .. } => Kind::Problem,
+        LogEvent::Converted { result, .. } if result.below_min_ssim => Kind::Problem,
+        LogEvent::Converted { .. } | LogEvent::SourceRemoved { .. } => Kind::Converted,
+        LogEvent::Skipped { .. } => Kind::Skipped,
+        _ => Kind::Info,
+    }
+}
+
+/// The file an event is about, if any.
+fn event_path(event: &LogEvent) -> Option<&Path> {
+    match event {
+        LogEvent::SourceRemoved {
+            moved_to: Some(to), ..
+        } => Some(to),
+        LogEvent::Detected { path }
+        | LogEvent::Converted { path, .. }
+        | LogEvent::Skipped { path, .. }
+        | LogEvent::SourceRemoved { path, .. }
+        | LogEvent::Error {
+            path: Some(path), ..
+        } => Some(path),
+        _ => None,
+    }
+}
+
+pub struct LogView {
+    pub search: String,
+    pub show_converted: bool,
+    pub show_skipped: bool,
+    pub show_problems: bool,
+    cache: LineCache,
+}
+
+impl Default for LogView {
+    fn default() -> Self {
+        Self {
+            search: String::new(),
+            show_converted: true,
+            show_skipped: true,
+            show_problems: true,
+            cache: LineCache::default(),
+        }
+    }
+}
+
+struct Line {
+    kind: Kind,
+    text: String,
+    lower: String,
+    color: Color32,
+}
+
+/// Formatted lines for `RunLog::events`, index for index, and the indices
+/// that pass the filter they were last built for.
+#[derive(Default)]
+struct LineCache {
+    dropped: usize,
+    lines: Vec<Line>,
+    counts: [usize; 3],
+    filter: Option<(String, [bool; 3])>,
+    rows: Vec<usize>,
+}
+
+impl LogView {
+    fn shows(&self, kind: Kind) -> bool {
+        match kind {
+            Kind::Converted => self.show_converted,
+            Kind::Skipped => self.show_skipped,
+            Kind::Problem => self.show_problems,
+            Kind::Info => true,
+        }
+    }
+
+    fn passes(&self, line: &Line, needle: &str) -> bool {
+        self.shows(line.kind) && (needle.is_empty() || line.lower.contains(needle))
+    }
+
+    /// Brings the cache up to date with `log`, formatting only new events.
+    pub(crate) fn refresh(&mut self, log: &RunLog) {
+        let mut cache = std::mem::take(&mut self.cache);
+        if cache.dropped != log.dropped || cache.lines.len() > log.events.len() {
+            cache = LineCache {
+                dropped: log.dropped,
+                ..LineCache::default()
+            };
+        }
+
+        let toggles = [self.show_converted, self.show_skipped, self.show_problems];
+        let filter = (self.search.trim().to_lowercase(), toggles);
+        if cache.filter.as_ref() != Some(&filter) {
+            cache.rows = (0..cache.lines.len())
+                .filter(|&i| self.passes(&cache.lines[i], &filter.0))
+                .collect();
+            cache.filter = Some(filter);
+        }
+
+        let needle = cache.filter.as_ref().map_or("", |(needle, _)| needle.as_str());
+        for (i, event) in log.events.iter().enumerate().skip(cache.lines.len()) {
+            let kind = kind(event);
+            if let Some(count) = cache.counts.get_mut(kind as usize) {
+                *count += 1;
+            }
+            let (text, color) = format_event(event);
+            let line = Line {
+                kind,
+                lower: text.to_lowercase(),
+                text,
+                color,
+            };
+            if self.passes(&line, needle) {
+                cache.rows.push(i);
+            }
+            cache.lines.push(line);
+        }
+        self.cache = cache;
+    }
+
+    /// Indices into `RunLog::events` of the rows shown, as of the last
+    /// [`refresh`](Self::refresh).
+    pub(crate) fn rows(&self) -> &[usize] {
+        &self.cache.rows
+    }
+
+    /// Converted, skipped and problem events, shown or not.
+    pub(crate) fn counts(&self) -> [usize; 3] {
+        self.cache.counts
+    }
+
+    pub fn show(&mut self, ui: &mut egui::Ui, log: &RunLog) {
+        self.refresh(log);
+
+        ui.horizontal(|ui| {
+            ui.add(
+                egui::TextEdit::singleline(&mut self.search)
+                    .hint_text("Search log")
+                    .desired_width(160.0),
+            );
+            let [converted, skipped, problems] = self.counts();
+            toggle(
+                ui,
+                &mut self.show_converted,
+                format!("Converted ({converted})"),
+            );
+            toggle(ui, &mut self.show_skipped, format!("Skipped ({skipped})"));
+            toggle(ui, &mut self.show_problems, format!("Errors ({problems})"));
+        });
+
+        let (lines, rows) = (&self.cache.lines, self.rows());
+        egui::Frame::new()
+            .fill(Color32::from_rgb(20, 30, 40))
+            .stroke(egui::Stroke::new(1.0, Color32::GRAY))
+            .corner_radius(5)
+            .inner_margin(egui::Margin::same(8))
+            .show(ui, |ui| {
+                egui::ScrollArea::vertical()
+                    .auto_shrink([false; 2])
+                    .stick_to_bottom(true)
+                    .show_rows(ui, ROW_HEIGHT, rows.len(), |ui, range| {
+                        for &i in &rows[range] {
+                            let line = &lines[i];
+                            log_row(ui, &log.events[i], &line.text, line.color);
+                        }
+                    });
+            });
+    }
+}
+
+fn toggle(ui: &mut egui::Ui, on: &mut bool, label: String) {
+    if ui
+        .add(egui::Button::selectable(
+            *on,
+            RichText::new(label).size(12.0),
+        ))
+        .clicked()
+    {
+        *on = !*on;
+    }
+}
+
+/// One single-line entry; click copies it, right-click offers the file.
+fn log_row(ui: &mut egui::Ui, event: &LogEvent, text: &str, color: Color32) {
+    let label = egui::Label::new(RichText::new(text).size(12.0).color(color))
+        .truncate()
+        .sense(Sense::click());
+    let response = ui
+        .add_sized([ui.available_width(), ROW_HEIGHT], label)
+        .on_hover_text("Click to copy");
+    if response.clicked() {
+        ui.ctx().copy_text(text.to_string());
+    }
+    response.context_menu(|ui| {
+        if ui.button("Copy line").clicked() {
+            ui.ctx().copy_text(text.to_string());
+            ui.close();
+        }
+        let Some(path) = event_path(event) else {
+            return;
+        };
+        if ui.button("Copy path").clicked() {
+            ui.ctx().copy_text(path.display().to_string());
+            ui.close();
+        }
+        if ui
+            .add_enabled(path.exists(), egui::Button::new("Open file"))
+            .clicked()
+        {
+            open_in_system(path);
+            ui.close();
+        }
+        if let Some(folder) = path.parent()
+            && ui
+                .add_enabled(folder.exists(), egui::Button::new("Open folder"))
+                .clicked()
+        {
+            open_in_system(folder);
+            ui.close();
+        }
+    });
+}
+
+/// Hands `path` to the platform's default application.
+fn open_in_system(path: &Path) {
+    let opener = if cfg!(windows) {
+        "explorer"
+    } else if cfg!(target_os = "macos") {
+        "open"
+    } else {
+        "xdg-open"
+    };
+    // Nothing useful to report if there is no handler.
+    let _ = std::process::Command::new(opener).arg(path).spawn();
+}
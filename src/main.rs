@@ -2,16 +2,197 @@
 
 use eframe::NativeOptions;
 use eframe::egui::{self, Color32, FontFamily, FontId, RichText, Style};
-use std::path::Path;
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
-use webp_converter_app::{LogEvent, convert_to_webp_dir_threads};
+use std::time::{Duration, Instant};
+mod cli;
+mod jobs;
+mod log_file;
+mod log_view;
+mod preset_panel;
+mod preview;
+mod savings;
+mod settings;
+
+use jobs::{Job, JobSpec, JobState};
+use log_file::{LogFile, LogFileSettings, LogFormat};
+use log_view::{LogView, RunLog};
+use preset_panel::{PresetDialogResult, preset_from_storage};
+use preview::Preview;
+use savings::Savings;
+use settings::{LogSettings, SETTINGS_VERSION, Settings, Unknown};
+use webp_converter_app::{
+    ConvertOptions, DiscoveryFilter, Journal, JournalState, LogEvent, OriginalCopy, Preset,
+    RunControl, SUPPORTED_EXTENSIONS, SkipReason, SourceAction, is_supported_image, resume_run,
+    retry_failed, watch_dir_threads,
+};
 
 const STORAGE_KEY: &str = "webp_converter_app_state";
 const LOG_CAP: usize = 5000;
 const LOG_TRIM_TO: usize = 4000;
+/// How far back the throughput shown during a run looks.
+const RATE_WINDOW: Duration = Duration::from_secs(10);
+/// Upper bounds of the limits, in the units they are edited in, so that
+/// stored values neither overflow nor exceed what TOML can hold.
+const MAX_FILE_SIZE_KB: u64 = 1 << 30;
+const MAX_DIMENSION: u32 = 1 << 20;
+const MAX_WORKERS: usize = 1024;
+const MAX_MEMORY_BUDGET_MB: u64 = 1 << 20;
+const MAX_MEGAPIXELS: u64 = 1 << 20;
+
+type LogFn = Box<dyn Fn(LogEvent) + Send + Sync>;
+
+/// A run that may need confirmation before it starts.
+#[derive(Clone, Copy)]
+enum RunKind {
+    Convert,
+    Retry,
+    Watch,
+    Jobs,
+}
+
+/// Per-run counters fed from the `LogEvent` stream.
+#[derive(Default)]
+struct Progress {
+    total: AtomicUsize,
+    done: AtomicUsize,
+    errors: AtomicUsize,
+    paused: AtomicBool,
+    /// Runs whose directory scan has not finished; totals are provisional.
+    scanning: AtomicUsize,
+    timing: Mutex<Timing>,
+    savings: Mutex<Savings>,
+}
+
+#[derive(Default)]
+struct Timing {
+    started: Option<Instant>,
+    finished: Option<Instant>,
+    /// When each file in the last [`RATE_WINDOW`] finished, with the
+    /// source bytes it read.
+    recent: VecDeque<(Instant, u64)>,
+}
+
+/// Speeds over the last [`RATE_WINDOW`] of a run.
+struct Throughput {
+    elapsed: Duration,
+    files_per_sec: f64,
+    bytes_per_sec: f64,
+}
+
+impl Progress {
+    fn reset(&self) {
+        self.paused.store(false, Ordering::Relaxed);
+        self.scanning.store(0, Ordering::Relaxed);
+        self.total.store(0, Ordering::Relaxed);
+        self.done.store(0, Ordering::Relaxed);
+        self.errors.store(0, Ordering::Relaxed);
+        if let Ok(mut timing) = self.timing.lock() {
+            *timing = Timing::default();
+        }
+        if let Ok(mut savings) = self.savings.lock() {
+            savings.clear();
+        }
+    }
+
+    fn finish_file(&self, bytes: u64) {
+        self.done.fetch_add(1, Ordering::Relaxed);
+        if let Ok(mut timing) = self.timing.lock() {
+            let now = Instant::now();
+            timing.recent.push_back((now, bytes));
+            while timing.recent.front().is_some_and(|(at, _)| now - *at > RATE_WINDOW) {
+                timing.recent.pop_front();
+            }
+        }
+    }
+
+    fn throughput(&self) -> Option<Throughput> {
+        let timing = self.timing.lock().ok()?;
+        let started = timing.started?;
+        let now = timing.finished.unwrap_or_else(Instant::now);
+        let window_start = started.max(now.checked_sub(RATE_WINDOW).unwrap_or(started));
+        let secs = (now - window_start).as_secs_f64().max(0.001);
+        let (files, bytes) = timing
+            .recent
+            .iter()
+            .filter(|(at, _)| *at >= window_start)
+            .fold((0, 0), |(n, b), (_, bytes)| (n + 1, b + bytes));
+        Some(Throughput {
+            elapsed: now - started,
+            files_per_sec: f64::from(files) / secs,
+            bytes_per_sec: bytes as f64 / secs,
+        })
+    }
+
+    fn record(&self, event: &LogEvent) {
+        if let Ok(mut savings) = self.savings.lock() {
+            savings.record(event);
+        }
+        match event {
+            LogEvent::Started { .. } => {
+                self.scanning.fetch_add(1, Ordering::Relaxed);
+                self.start_clock();
+            }
+            LogEvent::Watching { .. } => self.start_clock(),
+            LogEvent::Discovered { found, complete, .. } => {
+                self.total.fetch_add(*found, Ordering::Relaxed);
+                if *complete {
+                    let _ = self.scanning.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| {
+                        n.checked_sub(1)
+                    });
+                }
+            }
+            LogEvent::Detected { .. } => {
+                self.total.fetch_add(1, Ordering::Relaxed);
+            }
+            LogEvent::Converted { result, .. } => self.finish_file(result.source_len),
+            LogEvent::Skipped { reason, .. } => match reason {
+                SkipReason::NotSmaller { source_len, .. } => self.finish_file(*source_len),
+                _ => self.finish_file(0),
+            },
+            LogEvent::Error { path: Some(_), .. } => {
+                self.finish_file(0);
+                self.errors.fetch_add(1, Ordering::Relaxed);
+            }
+            LogEvent::Finished { .. } => {
+                if let Ok(mut timing) = self.timing.lock() {
+                    timing.finished = Some(Instant::now());
+                }
+            }
+            LogEvent::Paused => self.paused.store(true, Ordering::Relaxed),
+            LogEvent::Resumed => self.paused.store(false, Ordering::Relaxed),
+            _ => {}
+        }
+    }
+
+    fn start_clock(&self) {
+        if let Ok(mut timing) = self.timing.lock() {
+            timing.started.get_or_insert_with(Instant::now);
+        }
+    }
+
+    /// Time left at `files_per_sec`, shown only while a running batch
+    /// moves towards a known total.
+    fn eta(&self, files_per_sec: f64, running: bool) -> Option<Duration> {
+        let scanning = self.scanning.load(Ordering::Relaxed) > 0;
+        let paused = self.paused.load(Ordering::Relaxed);
+        let remaining = self
+            .total
+            .load(Ordering::Relaxed)
+            .saturating_sub(self.done.load(Ordering::Relaxed));
+        (running && !scanning && !paused && remaining > 0 && files_per_sec > 0.0)
+            .then(|| Duration::from_secs_f64(remaining as f64 / files_per_sec))
+    }
+}
 
 fn main() -> Result<(), eframe::Error> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if !args.is_empty() {
+        std::process::exit(cli::run(&args));
+    }
+
     let options = NativeOptions {
         viewport: egui::ViewportBuilder::default()
             .with_inner_size([620.0, 400.0])
@@ -37,45 +218,209 @@ struct MyApp {
     output_path: String,
     quality: f32,
     lossless: bool,
-    log_errors_only: bool,
+    auto_lossless: bool,
+    include_patterns: String,
+    exclude_patterns: String,
+    filter: DiscoveryFilter,
+    measure_quality: bool,
+    min_ssim: Option<f64>,
+    raise_quality: bool,
+    min_savings: Option<f32>,
+    copy_original: bool,
+    source_action: SourceAction,
+    confirm_run: Option<RunKind>,
+    in_place: bool,
+    preserve_times: bool,
+    preserve_permissions: bool,
+    exif_mtime: bool,
+    workers: Option<usize>,
+    memory_budget: Option<u64>,
+    max_pixels: Option<u64>,
+    downscale_large: bool,
+    log_view: LogView,
+    presets: Vec<Preset>,
+    selected_preset: Option<usize>,
+    /// Name typed for saving or renaming a preset.
+    preset_name: String,
+    preset_status: Option<String>,
+    preset_dialog: PresetDialogResult,
+    log_to_file: bool,
+    log_dir: String,
+    log_format: LogFormat,
+    /// Log files kept in `log_dir`; older ones are deleted.
+    log_keep: usize,
+    files: Vec<PathBuf>,
+    jobs: Arc<Mutex<Vec<Job>>>,
+    next_job_id: u64,
+    jobs_concurrent: bool,
 
-    log: Arc<Mutex<Vec<LogEvent>>>,
+    log: Arc<Mutex<RunLog>>,
     is_running: Arc<AtomicBool>,
-    total: Arc<AtomicUsize>,
-    done: Arc<AtomicUsize>,
-    errors: Arc<AtomicUsize>,
+    progress: Arc<Progress>,
+    control: RunControl,
+    failed: Arc<Mutex<Vec<PathBuf>>>,
+    watch_stop: Option<Arc<AtomicBool>>,
+    preview: Preview,
+    /// Journal left behind by a run that never finished, with how many of
+    /// its sources are left, counted once on load.
+    interrupted: Option<(JournalState, usize)>,
 
     validation_error: Option<String>,
     pending_input: Arc<Mutex<Option<String>>>,
     pending_output: Arc<Mutex<Option<String>>>,
+    pending_files: Arc<Mutex<Option<Vec<PathBuf>>>>,
+    /// Stored settings keys this version does not know, written back on save.
+    unknown_settings: Vec<Unknown>,
+    /// Stored settings that could not be read. They are saved back as they
+    /// were rather than replaced by defaults.
+    unreadable_settings: Option<String>,
 }
 
-impl MyApp {
-    fn new(cc: &eframe::CreationContext<'_>) -> Self {
-        let mut app = Self {
+impl Default for MyApp {
+    fn default() -> Self {
+        Self {
             input_path: String::new(),
             output_path: String::new(),
             quality: 87.0,
             lossless: false,
-            log_errors_only: false,
-            log: Arc::new(Mutex::new(Vec::new())),
+            auto_lossless: false,
+            include_patterns: String::new(),
+            exclude_patterns: String::new(),
+            filter: DiscoveryFilter::default(),
+            measure_quality: false,
+            min_ssim: None,
+            raise_quality: false,
+            min_savings: None,
+            copy_original: false,
+            source_action: SourceAction::Keep,
+            confirm_run: None,
+            in_place: false,
+            preserve_times: false,
+            preserve_permissions: false,
+            exif_mtime: false,
+            workers: None,
+            memory_budget: None,
+            max_pixels: None,
+            downscale_large: false,
+            log_view: LogView::default(),
+            presets: Vec::new(),
+            selected_preset: None,
+            preset_name: String::new(),
+            preset_status: None,
+            preset_dialog: Arc::new(Mutex::new(None)),
+            log_to_file: false,
+            log_dir: data_dir()
+                .map(|d| d.join("logs").display().to_string())
+                .unwrap_or_default(),
+            log_format: LogFormat::Text,
+            log_keep: 30,
+            files: Vec::new(),
+            jobs: Arc::new(Mutex::new(Vec::new())),
+            next_job_id: 0,
+            jobs_concurrent: false,
+            log: Arc::new(Mutex::new(RunLog::default())),
             is_running: Arc::new(AtomicBool::new(false)),
-            total: Arc::new(AtomicUsize::new(0)),
-            done: Arc::new(AtomicUsize::new(0)),
-            errors: Arc::new(AtomicUsize::new(0)),
+            progress: Arc::new(Progress::default()),
+            control: RunControl::default(),
+            failed: Arc::new(Mutex::new(Vec::new())),
+            watch_stop: None,
+            preview: Preview::default(),
+            interrupted: None,
             validation_error: None,
             pending_input: Arc::new(Mutex::new(None)),
             pending_output: Arc::new(Mutex::new(None)),
+            pending_files: Arc::new(Mutex::new(None)),
+            unknown_settings: Vec::new(),
+            unreadable_settings: None,
+        }
+    }
+}
+
+impl MyApp {
+    fn new(cc: &eframe::CreationContext<'_>) -> Self {
+        let mut app = Self {
+            interrupted: journal_path()
+                .and_then(|p| JournalState::load(&p).ok().flatten())
+                .map(|state| {
+                    let left = state.remaining().len();
+                    (state, left)
+                }),
+            ..Self::default()
         };
-        if let Some(storage) = cc.storage {
-            if let Some(raw) = storage.get_string(STORAGE_KEY) {
-                app.load_from_storage(&raw);
-            }
+        if let Some(storage) = cc.storage
+            && let Some(raw) = storage.get_string(STORAGE_KEY)
+        {
+            app.load_from_storage(&raw);
         }
         app
     }
 
     fn load_from_storage(&mut self, raw: &str) {
+        match Settings::parse(raw) {
+            Some(Ok((settings, unknown))) => {
+                self.apply_settings(settings);
+                self.unknown_settings = unknown;
+            }
+            Some(Err(e)) => {
+                let msg = format!("Ignoring saved settings, which are kept as they are: {e:#}");
+                push_log(&self.log, LogEvent::Error { path: None, msg });
+                self.unreadable_settings = Some(raw.to_string());
+            }
+            None => {
+                self.load_legacy(raw);
+                // Legacy values were never range-checked.
+                self.set_options(self.options());
+            }
+        }
+    }
+
+    fn apply_settings(&mut self, settings: Settings) {
+        self.input_path = settings.input_path;
+        self.output_path = settings.output_path;
+        self.set_options(settings.options);
+        self.jobs_concurrent = settings.jobs_concurrent;
+        self.log_view.show_converted = settings.log.show_converted;
+        self.log_view.show_skipped = settings.log.show_skipped;
+        self.log_view.show_problems = settings.log.show_errors;
+        self.log_to_file = settings.log.to_file;
+        self.log_dir = settings.log.dir;
+        self.log_format = settings.log.format;
+        self.log_keep = settings.log.keep.max(1);
+        self.presets = settings.presets;
+        for job in settings.jobs {
+            let (spec, state) = Job::from_settings(job);
+            self.push_job(spec, state);
+        }
+    }
+
+    fn settings(&self) -> Settings {
+        let jobs = match self.jobs.lock() {
+            Ok(jobs) => jobs.iter().filter_map(Job::to_settings).collect(),
+            Err(_) => Vec::new(),
+        };
+        Settings {
+            version: SETTINGS_VERSION,
+            input_path: self.input_path.clone(),
+            output_path: self.output_path.clone(),
+            options: self.options(),
+            jobs_concurrent: self.jobs_concurrent,
+            log: LogSettings {
+                show_converted: self.log_view.show_converted,
+                show_skipped: self.log_view.show_skipped,
+                show_errors: self.log_view.show_problems,
+                to_file: self.log_to_file,
+                dir: self.log_dir.clone(),
+                format: self.log_format,
+                keep: self.log_keep,
+            },
+            presets: self.presets.clone(),
+            jobs,
+        }
+    }
+
+    /// Reads the `key=value` lines written before settings were versioned.
+    /// The next save replaces them with the current format.
+    fn load_legacy(&mut self, raw: &str) {
         for line in raw.lines() {
             let Some((k, v)) = line.split_once('=') else { continue };
             match k {
@@ -87,17 +432,118 @@ impl MyApp {
                     }
                 }
                 "lossless" => self.lossless = v == "true",
-                "log_errors_only" => self.log_errors_only = v == "true",
+                "auto_lossless" => self.auto_lossless = v == "true",
+                "include_patterns" => self.include_patterns = v.to_string(),
+                "exclude_patterns" => self.exclude_patterns = v.to_string(),
+                "min_file_size" => self.filter.min_file_size = v.parse().ok(),
+                "max_file_size" => self.filter.max_file_size = v.parse().ok(),
+                "min_width" => self.filter.min_width = v.parse().ok(),
+                "min_height" => self.filter.min_height = v.parse().ok(),
+                "measure_quality" => self.measure_quality = v == "true",
+                "min_ssim" => self.min_ssim = v.parse().ok(),
+                "raise_quality" => self.raise_quality = v == "true",
+                "min_savings" => self.min_savings = v.parse().ok(),
+                "copy_original" => self.copy_original = v == "true",
+                "source_action" => self.source_action = parse_source_action(v),
+                "in_place" => self.in_place = v == "true",
+                "preserve_times" => self.preserve_times = v == "true",
+                "preserve_permissions" => self.preserve_permissions = v == "true",
+                "exif_mtime" => self.exif_mtime = v == "true",
+                "workers" => self.workers = v.parse().ok(),
+                "memory_budget" => self.memory_budget = v.parse().ok(),
+                "max_pixels" => self.max_pixels = v.parse().ok(),
+                "downscale_large" => self.downscale_large = v == "true",
+                // Written by versions that only had an errors-only switch.
+                "log_errors_only" if v == "true" => {
+                    self.log_view.show_converted = false;
+                    self.log_view.show_skipped = false;
+                }
+                "log_show_converted" => self.log_view.show_converted = v == "true",
+                "log_show_skipped" => self.log_view.show_skipped = v == "true",
+                "log_show_errors" => self.log_view.show_problems = v == "true",
+                "log_to_file" => self.log_to_file = v == "true",
+                "log_dir" => self.log_dir = v.to_string(),
+                "log_format" => self.log_format = LogFormat::from_storage(v),
+                "log_keep" => self.log_keep = v.parse().unwrap_or(self.log_keep),
+                "jobs_concurrent" => self.jobs_concurrent = v == "true",
+                "preset" => {
+                    if let Some(preset) = preset_from_storage(v) {
+                        self.presets.push(preset);
+                    }
+                }
+                "job" => {
+                    if let Some(job) = Job::from_storage(self.next_job_id, v) {
+                        self.push_job(job.spec, job.state);
+                    }
+                }
                 _ => {}
             }
         }
     }
 
+    fn options(&self) -> ConvertOptions {
+        ConvertOptions {
+            quality: self.quality,
+            lossless: self.lossless,
+            auto_lossless: self.auto_lossless,
+            filter: DiscoveryFilter {
+                include: split_patterns(&self.include_patterns),
+                exclude: split_patterns(&self.exclude_patterns),
+                ..self.filter.clone()
+            },
+            measure_quality: self.measure_quality,
+            min_ssim: self.min_ssim,
+            raise_quality: self.raise_quality,
+            min_savings: self.min_savings,
+            copy_original: self.copy_original,
+            source_action: self.source_action.clone(),
+            in_place: self.in_place,
+            preserve_times: self.preserve_times,
+            preserve_permissions: self.preserve_permissions,
+            exif_mtime: self.exif_mtime,
+            workers: self.workers,
+            memory_budget: self.memory_budget,
+            max_pixels: self.max_pixels,
+            downscale_large: self.downscale_large,
+            control: self.control.clone(),
+            journal: None,
+        }
+    }
+
+    /// Shows `options` in the controls; the reverse of [`MyApp::options`].
+    fn set_options(&mut self, options: ConvertOptions) {
+        self.quality = options.quality.clamp(0.0, 100.0);
+        self.lossless = options.lossless;
+        self.auto_lossless = options.auto_lossless;
+        self.include_patterns = options.filter.include.join(", ");
+        self.exclude_patterns = options.filter.exclude.join(", ");
+        self.filter = options.filter;
+        let filter = &mut self.filter;
+        filter.min_file_size = filter.min_file_size.map(|v| v.min(MAX_FILE_SIZE_KB << 10));
+        filter.max_file_size = filter.max_file_size.map(|v| v.min(MAX_FILE_SIZE_KB << 10));
+        filter.min_width = filter.min_width.map(|v| v.min(MAX_DIMENSION));
+        filter.min_height = filter.min_height.map(|v| v.min(MAX_DIMENSION));
+        self.measure_quality = options.measure_quality;
+        self.min_ssim = options.min_ssim;
+        self.raise_quality = options.raise_quality;
+        self.min_savings = options.min_savings;
+        self.copy_original = options.copy_original;
+        self.source_action = options.source_action;
+        self.in_place = options.in_place;
+        self.preserve_times = options.preserve_times;
+        self.preserve_permissions = options.preserve_permissions;
+        self.exif_mtime = options.exif_mtime;
+        self.workers = options.workers.map(|v| v.min(MAX_WORKERS));
+        self.memory_budget = options.memory_budget.map(|v| v.min(MAX_MEMORY_BUDGET_MB << 20));
+        self.max_pixels = options.max_pixels.map(|v| v.min(MAX_MEGAPIXELS * 1_000_000));
+        self.downscale_large = options.downscale_large;
+    }
+
     fn validate(&self) -> Result<(), String> {
         if self.input_path.trim().is_empty() {
             return Err("Input directory is empty.".into());
         }
-        if self.output_path.trim().is_empty() {
+        if !self.in_place && self.output_path.trim().is_empty() {
             return Err("Output directory is empty.".into());
         }
         let in_path = Path::new(&self.input_path);
@@ -107,13 +553,190 @@ impl MyApp {
         if !in_path.is_dir() {
             return Err("Input path is not a directory.".into());
         }
-        if Path::new(&self.input_path) == Path::new(&self.output_path) {
-            return Err("Input and output directories must differ.".into());
+        if !self.in_place && Path::new(&self.input_path) == Path::new(&self.output_path) {
+            return Err(
+                "Input and output directories must differ; use In place to write beside \
+                 the sources."
+                    .into(),
+            );
+        }
+        self.validate_source_action()
+    }
+
+    fn validate_files(&self) -> Result<(), String> {
+        if !self.in_place && self.output_path.trim().is_empty() {
+            return Err("Output directory is empty.".into());
+        }
+        if let Some(missing) = self.files.iter().find(|f| !f.is_file()) {
+            return Err(format!("File no longer exists: {}", missing.display()));
+        }
+        self.validate_source_action()
+    }
+
+    fn validate_source_action(&self) -> Result<(), String> {
+        if let SourceAction::MoveTo(dir) = &self.source_action
+            && dir.as_os_str().is_empty()
+        {
+            return Err("Archive folder for originals is empty.".into());
         }
         Ok(())
     }
 
+    /// Starts `kind`, asking first when it would move or delete originals.
+    fn request_run(&mut self, kind: RunKind, ctx: &egui::Context) {
+        let removes_sources = match kind {
+            RunKind::Jobs => self.jobs.lock().is_ok_and(|jobs| {
+                jobs.iter().any(|j| {
+                    j.state == JobState::Pending
+                        && j.spec.options.source_action != SourceAction::Keep
+                })
+            }),
+            _ => self.source_action != SourceAction::Keep,
+        };
+        if removes_sources {
+            self.confirm_run = Some(kind);
+        } else {
+            self.start_run(kind, ctx);
+        }
+    }
+
+    fn start_run(&mut self, kind: RunKind, ctx: &egui::Context) {
+        match kind {
+            RunKind::Convert => self.convert_in_thread(ctx),
+            RunKind::Retry => self.retry_in_thread(ctx),
+            RunKind::Watch => self.toggle_watch(ctx),
+            RunKind::Jobs => self.run_jobs_in_thread(ctx),
+        }
+    }
+
     fn convert_in_thread(&mut self, ctx: &egui::Context) {
+        if !self.files.is_empty() {
+            self.convert_files_in_thread(ctx);
+            return;
+        }
+        if let Err(msg) = self.validate() {
+            self.validation_error = Some(msg);
+            return;
+        }
+        self.validation_error = None;
+
+        self.spawn_journaled(
+            ctx,
+            JobSpec {
+                input: self.input_path.clone(),
+                files: Vec::new(),
+                output: self.output_path.clone(),
+                options: self.options(),
+            },
+        );
+    }
+
+    fn convert_files_in_thread(&mut self, ctx: &egui::Context) {
+        if let Err(msg) = self.validate_files() {
+            self.validation_error = Some(msg);
+            return;
+        }
+        self.validation_error = None;
+
+        self.spawn_journaled(
+            ctx,
+            JobSpec {
+                input: self.input_path.clone(),
+                files: self.files.clone(),
+                output: self.output_path.clone(),
+                options: self.options(),
+            },
+        );
+    }
+
+    /// Runs `spec` while keeping a journal of it, so an interrupted run is
+    /// offered for resuming on the next launch.
+    fn spawn_journaled(&mut self, ctx: &egui::Context, mut spec: JobSpec) {
+        let journal = start_journal(&spec);
+        spec.options.journal = journal.clone();
+        self.interrupted = None;
+        self.spawn_run(ctx, move |log_fn| {
+            let result = spec.run(log_fn);
+            if let Some(journal) = journal {
+                let _ = journal.remove();
+            }
+            result
+        });
+    }
+
+    fn resume_in_thread(&mut self, ctx: &egui::Context) {
+        let Some((state, _)) = self.interrupted.take() else {
+            return;
+        };
+        let Some((mut spec, _)) = Job::from_journal(&state.settings) else {
+            self.validation_error = Some("The interrupted run's settings are unreadable.".into());
+            return;
+        };
+        spec.options.control = self.control.clone();
+        let journal = start_journal(&spec);
+        spec.options.journal = journal.clone();
+        self.spawn_run(ctx, move |log_fn| {
+            let result = resume_run(&state, &spec.output, &spec.options, log_fn);
+            if let Some(journal) = journal {
+                let _ = journal.remove();
+            }
+            result
+        });
+    }
+
+    fn ui_interrupted_run(&mut self, ui: &mut egui::Ui, ctx: &egui::Context) {
+        let Some((state, left)) = &self.interrupted else {
+            return;
+        };
+        let running = self.is_running.load(Ordering::Relaxed);
+        let total = state.discovered.len();
+        let text = match state.unfinished_scan() {
+            Some(dir) => format!(
+                "A previous run was interrupted while scanning {}, with {left} of the \
+                 {total} file(s) found so far left.",
+                dir.display()
+            ),
+            None => format!("A previous run was interrupted with {left} of {total} file(s) left."),
+        };
+        ui.horizontal_wrapped(|ui| {
+            ui.label(RichText::new(text).color(Color32::from_rgb(200, 180, 120)));
+            if ui.add_enabled(!running, egui::Button::new("Resume previous run")).clicked() {
+                self.resume_in_thread(ctx);
+            }
+            if ui.button("Discard").clicked() {
+                self.interrupted = None;
+                if let Some(path) = journal_path() {
+                    let _ = std::fs::remove_file(path);
+                }
+            }
+        });
+    }
+
+    fn retry_in_thread(&mut self, ctx: &egui::Context) {
+        if !self.in_place && self.output_path.trim().is_empty() {
+            self.validation_error = Some("Output directory is empty.".into());
+            return;
+        }
+        self.validation_error = None;
+
+        let failed = match self.failed.lock() {
+            Ok(failed) => failed.clone(),
+            Err(_) => return,
+        };
+        let output = self.output_path.clone();
+        let options = self.options();
+        self.spawn_run(ctx, move |log_fn| {
+            retry_failed(&failed, &output, &options, log_fn)
+        });
+    }
+
+    fn toggle_watch(&mut self, ctx: &egui::Context) {
+        if let Some(stop) = self.watch_stop.take() {
+            stop.store(true, Ordering::Relaxed);
+            // A paused watcher has to wake up to notice the stop.
+            self.control.resume();
+            return;
+        }
         if let Err(msg) = self.validate() {
             self.validation_error = Some(msg);
             return;
@@ -122,91 +745,107 @@ impl MyApp {
 
         let input = self.input_path.clone();
         let output = self.output_path.clone();
-        let quality = self.quality;
-        let lossless = self.lossless;
-        let log = self.log.clone();
+        let options = self.options();
+        let stop = Arc::new(AtomicBool::new(false));
+        self.watch_stop = Some(stop.clone());
+        self.spawn_run(ctx, move |log_fn| {
+            watch_dir_threads(&input, &output, &options, &stop, log_fn)
+        });
+    }
+
+    fn spawn_run<R>(&mut self, ctx: &egui::Context, run: R)
+    where
+        R: FnOnce(LogFn) -> anyhow::Result<()> + Send + 'static,
+    {
         let is_running = self.is_running.clone();
-        let total = self.total.clone();
-        let done = self.done.clone();
-        let errors = self.errors.clone();
-        let ctx = ctx.clone();
+        let output = self.begin_run(ctx);
 
+        std::thread::spawn(move || {
+            if let Err(e) = run(output.log_fn(None)) {
+                let event = LogEvent::Error {
+                    path: None,
+                    msg: format!("Fatal: {e:#}"),
+                };
+                write_log_file(output.log_file.as_deref(), &event);
+                push_log(&output.log, event);
+            }
+            is_running.store(false, Ordering::Relaxed);
+            output.ctx.request_repaint();
+        });
+    }
+
+    /// Resets the log, progress and failed list for a new run and marks the
+    /// app as running.
+    pub(crate) fn begin_run(&mut self, ctx: &egui::Context) -> RunOutput {
         if let Ok(mut log) = self.log.lock() {
             log.clear();
         }
-        total.store(0, Ordering::Relaxed);
-        done.store(0, Ordering::Relaxed);
-        errors.store(0, Ordering::Relaxed);
-        is_running.store(true, Ordering::Relaxed);
+        if let Ok(mut failed) = self.failed.lock() {
+            failed.clear();
+        }
+        let log_file = self.open_log_file();
+        self.control.resume();
+        self.progress.reset();
+        self.is_running.store(true, Ordering::Relaxed);
+        RunOutput {
+            log: self.log.clone(),
+            progress: self.progress.clone(),
+            failed: self.failed.clone(),
+            log_file,
+            ctx: ctx.clone(),
+        }
+    }
 
+    fn spawn_folder_picker(&self, slot: Arc<Mutex<Option<String>>>, ctx: &egui::Context) {
+        let ctx = ctx.clone();
         std::thread::spawn(move || {
-            let log_fn = {
-                let log = log.clone();
-                let ctx = ctx.clone();
-                let total = total.clone();
-                let done = done.clone();
-                let errors = errors.clone();
-                move |event: LogEvent| {
-                    match &event {
-                        LogEvent::Discovered { total: t } => {
-                            total.store(*t, Ordering::Relaxed);
-                        }
-                        LogEvent::Converted { .. } | LogEvent::Skipped { .. } => {
-                            done.fetch_add(1, Ordering::Relaxed);
-                        }
-                        LogEvent::Error { .. } => {
-                            done.fetch_add(1, Ordering::Relaxed);
-                            errors.fetch_add(1, Ordering::Relaxed);
-                        }
-                        _ => {}
-                    }
-                    if let Ok(mut log) = log.lock() {
-                        if log.len() >= LOG_CAP {
-                            let drop = log.len() - LOG_TRIM_TO;
-                            log.drain(0..drop);
-                        }
-                        log.push(event);
-                    }
-                    ctx.request_repaint();
-                }
-            };
-
-            if let Err(e) =
-                convert_to_webp_dir_threads(&input, &output, quality, lossless, log_fn)
-            {
-                if let Ok(mut log) = log.lock() {
-                    log.push(LogEvent::Error {
-                        msg: format!("Fatal: {}", e),
-                    });
+            if let Some(path) = rfd::FileDialog::new().pick_folder() {
+                if let Ok(mut s) = slot.lock() {
+                    *s = Some(path.display().to_string());
                 }
+                ctx.request_repaint();
             }
-            is_running.store(false, Ordering::Relaxed);
-            ctx.request_repaint();
         });
     }
 
-    fn spawn_folder_picker(&self, slot: Arc<Mutex<Option<String>>>, ctx: &egui::Context) {
+    fn spawn_files_picker(&self, ctx: &egui::Context) {
+        let slot = self.pending_files.clone();
         let ctx = ctx.clone();
         std::thread::spawn(move || {
-            if let Some(path) = rfd::FileDialog::new().pick_folder() {
+            if let Some(paths) = rfd::FileDialog::new()
+                .add_filter("Images", SUPPORTED_EXTENSIONS)
+                .pick_files()
+            {
                 if let Ok(mut s) = slot.lock() {
-                    *s = Some(path.display().to_string());
+                    *s = Some(paths);
                 }
                 ctx.request_repaint();
             }
         });
     }
 
-    fn drain_pending_pickers(&mut self) {
-        if let Ok(mut s) = self.pending_input.lock() {
-            if let Some(p) = s.take() {
-                self.input_path = p;
+    fn add_files(&mut self, paths: impl IntoIterator<Item = PathBuf>) {
+        for path in paths {
+            if !self.files.contains(&path) {
+                self.files.push(path);
             }
         }
-        if let Ok(mut s) = self.pending_output.lock() {
-            if let Some(p) = s.take() {
-                self.output_path = p;
-            }
+    }
+
+    fn drain_pending_pickers(&mut self) {
+        if let Ok(mut s) = self.pending_input.lock()
+            && let Some(p) = s.take()
+        {
+            self.input_path = p;
+        }
+        if let Ok(mut s) = self.pending_output.lock()
+            && let Some(p) = s.take()
+        {
+            self.output_path = p;
+        }
+        let picked = self.pending_files.lock().ok().and_then(|mut s| s.take());
+        if let Some(paths) = picked {
+            self.add_files(paths);
         }
     }
 
@@ -251,13 +890,21 @@ impl MyApp {
                 },
             );
             ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
-                if ui.add_sized([btn_w, row_h], egui::Button::new("📁")).clicked() {
+                let enabled = !self.in_place;
+                let folder = ui.add_enabled_ui(enabled, |ui| {
+                    ui.add_sized([btn_w, row_h], egui::Button::new("📁"))
+                });
+                if folder.inner.clicked() {
                     clicked_output = true;
                 }
-                ui.add_sized(
-                    [ui.available_width(), row_h],
-                    egui::TextEdit::singleline(&mut self.output_path),
-                );
+                ui.checkbox(&mut self.in_place, "In place")
+                    .on_hover_text("Write each WebP next to its source");
+                ui.add_enabled_ui(enabled, |ui| {
+                    ui.add_sized(
+                        [ui.available_width(), row_h],
+                        egui::TextEdit::singleline(&mut self.output_path),
+                    );
+                });
             });
         });
 
@@ -266,85 +913,427 @@ impl MyApp {
         }
     }
 
+    fn handle_dropped_files(&mut self, ctx: &egui::Context) {
+        let dropped = ctx.input(|i| i.raw.dropped_files.clone());
+        if dropped.is_empty() {
+            return;
+        }
+        let mut ignored = 0;
+        let mut files = Vec::new();
+        for path in dropped.into_iter().filter_map(|f| f.path) {
+            if path.is_dir() {
+                self.input_path = path.display().to_string();
+            } else if is_supported_image(&path) {
+                files.push(path);
+            } else {
+                ignored += 1;
+            }
+        }
+        self.add_files(files);
+        self.validation_error = (ignored > 0)
+            .then(|| format!("Ignored {ignored} dropped file(s) with unsupported type."));
+    }
+
+    fn paint_drop_overlay(&self, ctx: &egui::Context) {
+        if ctx.input(|i| i.raw.hovered_files.is_empty()) {
+            return;
+        }
+        let painter = ctx.layer_painter(egui::LayerId::new(
+            egui::Order::Foreground,
+            egui::Id::new("drop_overlay"),
+        ));
+        let rect = ctx.content_rect();
+        painter.rect_filled(rect, 0.0, Color32::from_black_alpha(190));
+        painter.rect_stroke(
+            rect.shrink(12.0),
+            8.0,
+            egui::Stroke::new(2.0, Color32::LIGHT_BLUE),
+            egui::StrokeKind::Inside,
+        );
+        painter.text(
+            rect.center(),
+            egui::Align2::CENTER_CENTER,
+            "Drop a folder to set the input,\nor images to add them to the file list",
+            FontId::proportional(20.0),
+            Color32::WHITE,
+        );
+    }
+
+    fn ui_files(&mut self, ui: &mut egui::Ui, ctx: &egui::Context) {
+        let running = self.is_running.load(Ordering::Relaxed);
+        let mut remove = None;
+        ui.horizontal(|ui| {
+            ui.add_enabled_ui(!running, |ui| {
+                if ui.button("Add files…").clicked() {
+                    self.spawn_files_picker(ctx);
+                }
+                if ui.button("Clear files").clicked() {
+                    self.files.clear();
+                }
+            });
+            let hint = if self.files.is_empty() {
+                "Converting the input directory".to_string()
+            } else {
+                format!(
+                    "Converting {} selected file(s) instead of the input directory",
+                    self.files.len()
+                )
+            };
+            ui.label(RichText::new(hint).size(13.0).color(Color32::GRAY));
+        });
+        if self.files.is_empty() {
+            return;
+        }
+        let header = RichText::new(format!("Files ({})", self.files.len())).size(14.0);
+        egui::CollapsingHeader::new(header)
+            .id_salt("file_queue")
+            .show(ui, |ui| {
+                egui::ScrollArea::vertical()
+                    .id_salt("file_queue_scroll")
+                    .max_height(90.0)
+                    .show(ui, |ui| {
+                        for (i, path) in self.files.iter().enumerate() {
+                            ui.horizontal(|ui| {
+                                let remove_btn = egui::Button::new("✖").small();
+                                if ui.add_enabled(!running, remove_btn).clicked() {
+                                    remove = Some(i);
+                                }
+                                ui.label(RichText::new(path.display().to_string()).size(12.0));
+                            });
+                        }
+                    });
+            });
+        if let Some(i) = remove {
+            self.files.remove(i);
+        }
+    }
+
     fn ui_convert_button(&mut self, ui: &mut egui::Ui, ctx: &egui::Context) {
         let running = self.is_running.load(Ordering::Relaxed);
-        let label = if running { "WORKING…" } else { "CONVERT 🔁" };
+        let label = match (running, self.watch_stop.is_some()) {
+            (true, true) => "WATCHING…",
+            (true, false) => "WORKING…",
+            _ => "CONVERT 🔁",
+        };
         let button = egui::Button::new(RichText::new(format!("\n{label}\n")));
         let response = ui.add_enabled_ui(!running, |ui| {
             ui.add_sized([ui.available_width(), 58.5], button)
         });
         if response.inner.clicked() {
-            self.convert_in_thread(ctx);
+            self.request_run(RunKind::Convert, ctx);
         }
     }
 
-    fn ui_controls(&mut self, ui: &mut egui::Ui) {
+    fn ui_controls(&mut self, ui: &mut egui::Ui, ctx: &egui::Context) {
         let running = self.is_running.load(Ordering::Relaxed);
-        ui.horizontal(|ui| {
+        let failed = self.failed.lock().map_or(0, |f| f.len());
+        if !running {
+            // The watcher may have stopped on its own, e.g. the folder vanished.
+            self.watch_stop = None;
+        }
+        let watching = self.watch_stop.is_some();
+        ui.horizontal_wrapped(|ui| {
             ui.label("Quality: ");
             ui.add_enabled(
-                !self.lossless && !running,
+                (!self.lossless || self.auto_lossless) && !running,
                 egui::Slider::new(&mut self.quality, 0.0..=100.0),
             );
-            if ui.button("Clear log").clicked() {
-                if let Ok(mut log) = self.log.lock() {
-                    log.clear();
-                }
+            if ui.button("Clear log").clicked()
+                && let Ok(mut log) = self.log.lock()
+            {
+                log.clear();
             }
             ui.add_enabled_ui(!running, |ui| {
-                ui.checkbox(&mut self.lossless, "Lossless");
+                ui.add_enabled(
+                    !self.auto_lossless,
+                    egui::Checkbox::new(&mut self.lossless, "Lossless"),
+                );
+                ui.checkbox(&mut self.auto_lossless, "Auto")
+                    .on_hover_text("Per image, keep whichever of lossy or lossless is smaller");
             });
-            ui.checkbox(&mut self.log_errors_only, "Show errors only");
+            let retry = ui.add_enabled(
+                !running && failed > 0,
+                egui::Button::new(format!("Retry failed ({failed})")),
+            );
+            if retry.clicked() {
+                self.request_run(RunKind::Retry, ctx);
+            }
+            let watch = ui
+                .add_enabled(
+                    !running || watching,
+                    egui::Button::selectable(watching, "👁 Watch folder"),
+                )
+                .on_hover_text("Convert new or changed images in the input directory");
+            if watch.clicked() && watching {
+                self.toggle_watch(ctx);
+            } else if watch.clicked() {
+                self.request_run(RunKind::Watch, ctx);
+            }
+            let paused = self.control.is_paused();
+            let pause_label = if paused { "▶ Resume" } else { "⏸ Pause" };
+            let pause = ui
+                .add_enabled(running, egui::Button::new(pause_label))
+                .on_hover_text("Stop starting new files; files in progress finish");
+            if pause.clicked() {
+                if paused {
+                    self.control.resume();
+                } else {
+                    self.control.pause();
+                }
+            }
+            ui.toggle_value(&mut self.preview.open, "🔍 Preview");
         });
     }
 
-    fn ui_progress(&self, ui: &mut egui::Ui) {
-        let total = self.total.load(Ordering::Relaxed);
-        let done = self.done.load(Ordering::Relaxed);
-        let errors = self.errors.load(Ordering::Relaxed);
+    fn ui_quality_checks(&mut self, ui: &mut egui::Ui) {
+        let running = self.is_running.load(Ordering::Relaxed);
+        ui.add_enabled_ui(!running, |ui| {
+            ui.horizontal_wrapped(|ui| {
+                ui.checkbox(&mut self.measure_quality, "Measure PSNR/SSIM")
+                    .on_hover_text("Decode each output and score it against the source");
+                let mut limited = self.min_ssim.is_some();
+                ui.checkbox(&mut limited, "Min SSIM");
+                if limited {
+                    let mut min = self.min_ssim.unwrap_or(0.95);
+                    ui.add(egui::DragValue::new(&mut min).speed(0.001).range(0.0..=1.0));
+                    self.min_ssim = Some(min);
+                    ui.checkbox(&mut self.raise_quality, "Raise quality to meet it");
+                } else {
+                    self.min_ssim = None;
+                }
+            });
+            ui.horizontal_wrapped(|ui| {
+                let mut limited = self.min_savings.is_some();
+                ui.checkbox(&mut limited, "Keep original unless WebP saves")
+                    .on_hover_text("Discard outputs that are not smaller by this margin");
+                if limited {
+                    let mut margin = self.min_savings.unwrap_or(0.0);
+                    ui.add(egui::DragValue::new(&mut margin).range(0.0..=100.0).suffix(" %"));
+                    self.min_savings = Some(margin);
+                    ui.checkbox(&mut self.copy_original, "Copy original to output");
+                } else {
+                    self.min_savings = None;
+                }
+            });
+        });
+    }
+
+    fn ui_source_action(&mut self, ui: &mut egui::Ui) {
         let running = self.is_running.load(Ordering::Relaxed);
-        if total == 0 && !running && done == 0 {
+        ui.add_enabled_ui(!running, |ui| {
+            ui.horizontal(|ui| {
+                ui.label("After conversion:");
+                let selected = match self.source_action {
+                    SourceAction::Keep => "Keep originals",
+                    SourceAction::MoveTo(_) => "Move originals to",
+                    SourceAction::Delete => "Delete originals",
+                };
+                egui::ComboBox::from_id_salt("source_action")
+                    .selected_text(selected)
+                    .show_ui(ui, |ui| {
+                        let action = &mut self.source_action;
+                        if ui
+                            .selectable_label(*action == SourceAction::Keep, "Keep originals")
+                            .clicked()
+                        {
+                            *action = SourceAction::Keep;
+                        }
+                        let moving = matches!(action, SourceAction::MoveTo(_));
+                        if ui.selectable_label(moving, "Move originals to").clicked() && !moving {
+                            *action = SourceAction::MoveTo(PathBuf::new());
+                        }
+                        if ui
+                            .selectable_label(*action == SourceAction::Delete, "Delete originals")
+                            .clicked()
+                        {
+                            *action = SourceAction::Delete;
+                        }
+                    });
+                if let SourceAction::MoveTo(dir) = &mut self.source_action {
+                    let mut text = dir.display().to_string();
+                    let edit = egui::TextEdit::singleline(&mut text).hint_text("Archive folder");
+                    if ui.add(edit).changed() {
+                        *dir = PathBuf::from(text);
+                    }
+                }
+            });
+            ui.horizontal_wrapped(|ui| {
+                ui.checkbox(&mut self.preserve_times, "Keep timestamps");
+                ui.checkbox(&mut self.preserve_permissions, "Keep permissions");
+                ui.checkbox(&mut self.exif_mtime, "Date from EXIF")
+                    .on_hover_text("Set the modified time from the photo's DateTimeOriginal");
+            });
+        });
+    }
+
+    /// Asks before a run that moves or deletes originals.
+    fn ui_confirm_run(&mut self, ctx: &egui::Context) {
+        let Some(kind) = self.confirm_run else {
             return;
-        }
-        let frac = if total == 0 {
-            0.0
-        } else {
-            (done as f32 / total as f32).clamp(0.0, 1.0)
         };
-        let text = if total == 0 {
-            "Scanning…".to_string()
-        } else {
-            format!("{done}/{total}  ✓ {} · ✗ {errors}", done.saturating_sub(errors))
+        let what = match (kind, &self.source_action) {
+            (RunKind::Jobs, _) => "Some queued jobs will move or delete their originals".into(),
+            (_, SourceAction::MoveTo(dir)) => {
+                format!("Originals will be moved to {}", dir.display())
+            }
+            _ => "Originals will be permanently deleted".into(),
         };
-        ui.add(
-            egui::ProgressBar::new(frac)
-                .text(RichText::new(text).size(13.0))
-                .desired_width(ui.available_width()),
-        );
+        let mut choice = None;
+        let modal = egui::Modal::new(egui::Id::new("confirm_run")).show(ctx, |ui| {
+            ui.heading("Remove originals?");
+            ui.label(format!("{what} once each WebP is written and verified."));
+            ui.add_space(6.0);
+            ui.horizontal(|ui| {
+                if ui.button("Continue").clicked() {
+                    choice = Some(true);
+                }
+                if ui.button("Cancel").clicked() {
+                    choice = Some(false);
+                }
+            });
+        });
+        if modal.should_close() && choice.is_none() {
+            choice = Some(false);
+        }
+        if let Some(confirmed) = choice {
+            self.confirm_run = None;
+            if confirmed {
+                self.start_run(kind, ctx);
+            }
+        }
     }
 
-    fn ui_log(&self, ui: &mut egui::Ui) {
-        egui::Frame::new()
-            .fill(Color32::from_rgb(20, 30, 40))
-            .stroke(egui::Stroke::new(1.0, Color32::GRAY))
-            .corner_radius(5)
-            .inner_margin(egui::Margin::same(8))
+    fn ui_filters(&mut self, ui: &mut egui::Ui) {
+        let running = self.is_running.load(Ordering::Relaxed);
+        let active = !self.options().filter.is_empty();
+        let title = if active { "Filters (active)" } else { "Filters" };
+        egui::CollapsingHeader::new(RichText::new(title).size(14.0))
+            .id_salt("filters")
             .show(ui, |ui| {
-                egui::ScrollArea::vertical()
-                    .auto_shrink([false; 2])
-                    .stick_to_bottom(true)
-                    .show(ui, |ui| {
-                        let Ok(log) = self.log.lock() else { return };
-                        for event in log.iter() {
-                            if self.log_errors_only && !is_error_or_summary(event) {
-                                continue;
-                            }
-                            let (text, color) = format_event(event);
-                            ui.label(RichText::new(text).size(12.0).color(color));
-                        }
+                ui.add_enabled_ui(!running, |ui| {
+                    egui::Grid::new("filter_grid").num_columns(2).show(ui, |ui| {
+                        ui.label("Include:");
+                        ui.add(
+                            egui::TextEdit::singleline(&mut self.include_patterns)
+                                .hint_text("e.g. *.png, hero_*"),
+                        );
+                        ui.end_row();
+                        ui.label("Exclude:");
+                        ui.add(
+                            egui::TextEdit::singleline(&mut self.exclude_patterns)
+                                .hint_text("e.g. *_raw.*, thumb_*"),
+                        );
+                        ui.end_row();
+                        ui.label("File size (KB):");
+                        ui.horizontal(|ui| {
+                            let max = MAX_FILE_SIZE_KB;
+                            optional_limit(ui, "min", &mut self.filter.min_file_size, 1024, max);
+                            optional_limit(ui, "max", &mut self.filter.max_file_size, 1024, max);
+                        });
+                        ui.end_row();
+                        ui.label("Min pixels:");
+                        ui.horizontal(|ui| {
+                            let max = MAX_DIMENSION;
+                            optional_limit(ui, "width", &mut self.filter.min_width, 1, max);
+                            optional_limit(ui, "height", &mut self.filter.min_height, 1, max);
+                        });
+                        ui.end_row();
+                    });
+                });
+            });
+    }
+
+    fn ui_log_files(&mut self, ui: &mut egui::Ui) {
+        let running = self.is_running.load(Ordering::Relaxed);
+        egui::CollapsingHeader::new(RichText::new("Log files").size(14.0))
+            .id_salt("log_files")
+            .show(ui, |ui| {
+                ui.add_enabled_ui(!running, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.checkbox(&mut self.log_to_file, "Write each run to");
+                        ui.add_enabled(
+                            self.log_to_file,
+                            egui::TextEdit::singleline(&mut self.log_dir).hint_text("Log folder"),
+                        );
+                    });
+                    ui.add_enabled_ui(self.log_to_file, |ui| {
+                        ui.horizontal(|ui| {
+                            let format = &mut self.log_format;
+                            ui.radio_value(format, LogFormat::Text, "Plain text");
+                            ui.radio_value(format, LogFormat::JsonLines, "JSON lines");
+                            ui.label("Keep");
+                            ui.add(egui::DragValue::new(&mut self.log_keep).range(1..=10_000));
+                            ui.label("newest");
+                        });
                     });
+                });
             });
     }
+
+    /// Opens this run's log file if enabled. Failing to is logged and the
+    /// run goes ahead without one.
+    pub(crate) fn open_log_file(&self) -> Option<Arc<LogFile>> {
+        if !self.log_to_file || self.log_dir.trim().is_empty() {
+            return None;
+        }
+        let settings = LogFileSettings {
+            dir: PathBuf::from(self.log_dir.trim()),
+            format: self.log_format,
+            keep: self.log_keep,
+        };
+        match LogFile::create(&settings) {
+            Ok(file) => Some(Arc::new(file)),
+            Err(e) => {
+                let msg = format!("Not writing a log file: {e:#}");
+                push_log(&self.log, LogEvent::Error { path: None, msg });
+                None
+            }
+        }
+    }
+
+    fn ui_performance(&mut self, ui: &mut egui::Ui) {
+        let running = self.is_running.load(Ordering::Relaxed);
+        egui::CollapsingHeader::new(RichText::new("Performance").size(14.0))
+            .id_salt("performance")
+            .show(ui, |ui| {
+                ui.add_enabled_ui(!running, |ui| {
+                    ui.horizontal_wrapped(|ui| {
+                        optional_limit(ui, "Worker threads", &mut self.workers, 1, MAX_WORKERS);
+                        let (budget, max) = (&mut self.memory_budget, MAX_MEMORY_BUDGET_MB);
+                        optional_limit(ui, "Memory budget (MB)", budget, 1 << 20, max);
+                    });
+                    ui.horizontal_wrapped(|ui| {
+                        let pixels = &mut self.max_pixels;
+                        optional_limit(ui, "Max megapixels", pixels, 1_000_000, MAX_MEGAPIXELS);
+                        ui.add_enabled(
+                            self.max_pixels.is_some(),
+                            egui::Checkbox::new(
+                                &mut self.downscale_large,
+                                "Downscale larger JPEGs instead of skipping",
+                            ),
+                        );
+                    });
+                    ui.label(
+                        RichText::new("Unchecked uses every core and no memory or size limit.")
+                            .size(12.0)
+                            .color(Color32::GRAY),
+                    );
+                });
+            });
+    }
+
+    fn ui_progress(&self, ui: &mut egui::Ui) {
+        let running = self.is_running.load(Ordering::Relaxed);
+        progress_bar(ui, &self.progress, running);
+        if !running && let Ok(savings) = self.progress.savings.lock() {
+            savings.show(ui);
+        }
+    }
+
+    fn ui_log(&mut self, ui: &mut egui::Ui) {
+        let Ok(log) = self.log.lock() else { return };
+        self.log_view.show(ui, &log);
+    }
 }
 
 impl eframe::App for MyApp {
@@ -352,6 +1341,8 @@ impl eframe::App for MyApp {
         self.drain_pending_pickers();
 
         let ctx = ui.ctx().clone();
+        self.handle_dropped_files(&ctx);
+        self.paint_drop_overlay(&ctx);
         let panel_frame = egui::Frame::central_panel(&ctx.global_style())
             .inner_margin(egui::Margin::symmetric(12, 10));
 
@@ -386,24 +1377,239 @@ impl eframe::App for MyApp {
                 ui.colored_label(Color32::from_rgb(240, 120, 120), err);
             }
 
+            self.ui_interrupted_run(ui, &ctx);
+            self.ui_files(ui, &ctx);
+            self.ui_jobs(ui, &ctx);
+
             ui.separator();
-            self.ui_controls(ui);
+            self.ui_controls(ui, &ctx);
+            self.ui_presets(ui, &ctx);
+            self.ui_quality_checks(ui);
+            self.ui_source_action(ui);
+            self.ui_filters(ui);
+            self.ui_performance(ui);
+            self.ui_log_files(ui);
             self.ui_progress(ui);
             ui.add_space(5.0);
             self.ui_log(ui);
         });
+
+        self.ui_preview(&ctx);
+        self.ui_confirm_run(&ctx);
     }
 
     fn save(&mut self, storage: &mut dyn eframe::Storage) {
-        let serialized = format!(
-            "input_path={}\noutput_path={}\nquality={}\nlossless={}\nlog_errors_only={}",
-            self.input_path,
-            self.output_path,
-            self.quality,
-            self.lossless,
-            self.log_errors_only,
-        );
-        storage.set_string(STORAGE_KEY, serialized);
+        if let Some(raw) = &self.unreadable_settings {
+            storage.set_string(STORAGE_KEY, raw.clone());
+            return;
+        }
+        // On failure the previously stored settings are left as they were.
+        match self.settings().to_storage(&self.unknown_settings) {
+            Ok(serialized) => storage.set_string(STORAGE_KEY, serialized),
+            Err(e) => {
+                let msg = format!("Settings were not saved: {e:#}");
+                push_log(&self.log, LogEvent::Error { path: None, msg });
+            }
+        }
+    }
+}
+
+/// The app's folder in the per-user data directory.
+fn data_dir() -> Option<PathBuf> {
+    let base = if cfg!(windows) {
+        std::env::var_os("APPDATA").map(PathBuf::from)
+    } else {
+        std::env::var_os("XDG_DATA_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|h| Path::new(&h).join(".local/share")))
+    };
+    Some(base?.join("webp-converter-app"))
+}
+
+/// Where the journal of the current batch lives, in the per-user data
+/// folder so it survives reboots.
+fn journal_path() -> Option<PathBuf> {
+    Some(data_dir()?.join("run.journal"))
+}
+
+/// Where a run's events go: progress, the failed list, the log file and the
+/// on-screen log.
+#[derive(Clone)]
+pub(crate) struct RunOutput {
+    log: Arc<Mutex<RunLog>>,
+    progress: Arc<Progress>,
+    failed: Arc<Mutex<Vec<PathBuf>>>,
+    log_file: Option<Arc<LogFile>>,
+    pub(crate) ctx: egui::Context,
+}
+
+impl RunOutput {
+    /// A callback for one run; jobs also pass their own progress.
+    pub(crate) fn log_fn(&self, job_progress: Option<Arc<Progress>>) -> LogFn {
+        let output = self.clone();
+        Box::new(move |event: LogEvent| {
+            if let Some(job_progress) = &job_progress {
+                job_progress.record(&event);
+            }
+            output.progress.record(&event);
+            if let LogEvent::Error { path: Some(path), .. } = &event
+                && let Ok(mut failed) = output.failed.lock()
+            {
+                failed.push(path.clone());
+            }
+            write_log_file(output.log_file.as_deref(), &event);
+            push_log(&output.log, event);
+            output.ctx.request_repaint();
+        })
+    }
+}
+
+/// Interim scan counts are left out, as in the on-screen log.
+fn write_log_file(file: Option<&LogFile>, event: &LogEvent) {
+    if let Some(file) = file
+        && !matches!(event, LogEvent::Discovered { complete: false, .. })
+    {
+        file.write(event);
+    }
+}
+
+/// Starts journaling `spec`. Runs go ahead unjournaled if this fails.
+fn start_journal(spec: &JobSpec) -> Option<Arc<Journal>> {
+    let settings = Job::new(0, spec.clone(), JobState::Pending).to_journal()?;
+    Journal::create(&journal_path()?, &settings).ok().map(Arc::new)
+}
+
+/// Splits a comma-separated list of glob patterns from a text field.
+fn split_patterns(text: &str) -> Vec<String> {
+    text.split(',')
+        .map(str::trim)
+        .filter(|p| !p.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+/// Reads a `source_action` value of the legacy settings format.
+fn parse_source_action(text: &str) -> SourceAction {
+    match text {
+        "delete" => SourceAction::Delete,
+        _ => match text.strip_prefix("move:") {
+            Some(dir) => SourceAction::MoveTo(PathBuf::from(dir)),
+            None => SourceAction::Keep,
+        },
+    }
+}
+
+/// A checkbox that enables a limit plus a drag value editing it, shown in
+/// `unit`s of the stored value and capped at `max` of them.
+fn optional_limit<T: egui::emath::Numeric>(
+    ui: &mut egui::Ui,
+    label: &str,
+    value: &mut Option<T>,
+    unit: T,
+    max: T,
+) {
+    let mut enabled = value.is_some();
+    ui.checkbox(&mut enabled, label);
+    if !enabled {
+        *value = None;
+        return;
+    }
+    let (unit, max) = (unit.to_f64(), max.to_f64());
+    let mut shown = T::from_f64(value.map_or(0.0, |v| (v.to_f64() / unit).min(max)));
+    ui.add(egui::DragValue::new(&mut shown).range(0.0..=max));
+    // Float to integer casts saturate, so this cannot overflow.
+    *value = Some(T::from_f64(shown.to_f64().clamp(0.0, max) * unit));
+}
+
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit + 1 < UNITS.len() {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{value:.1} {}", UNITS[unit])
+    }
+}
+
+fn format_rate(files_per_sec: f64, bytes_per_sec: f64) -> String {
+    format!(
+        "{files_per_sec:.1} files/s · {:.1} MB/s",
+        bytes_per_sec / (1024.0 * 1024.0)
+    )
+}
+
+/// `m:ss`, or `h:mm:ss` from an hour up.
+fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    let (hours, minutes, secs) = (secs / 3600, secs / 60 % 60, secs % 60);
+    if hours > 0 {
+        format!("{hours}:{minutes:02}:{secs:02}")
+    } else {
+        format!("{minutes}:{secs:02}")
+    }
+}
+
+fn progress_bar(ui: &mut egui::Ui, progress: &Progress, running: bool) {
+    let total = progress.total.load(Ordering::Relaxed);
+    let done = progress.done.load(Ordering::Relaxed);
+    let errors = progress.errors.load(Ordering::Relaxed);
+    if total == 0 && !running && done == 0 {
+        return;
+    }
+    // Until every scan completes the total keeps growing, so the bar only
+    // animates instead of showing a fraction that would run backwards.
+    let scanning = running && progress.scanning.load(Ordering::Relaxed) > 0;
+    let frac = if total == 0 || scanning {
+        0.0
+    } else {
+        (done as f32 / total as f32).clamp(0.0, 1.0)
+    };
+    let mut text = if scanning {
+        format!("Scanning… {total} found · {done} done")
+    } else if total == 0 {
+        "Scanning…".to_string()
+    } else {
+        format!("{done}/{total}  ✓ {} · ✗ {errors}", done.saturating_sub(errors))
+    };
+    let paused = progress.paused.load(Ordering::Relaxed);
+    if let Some(rate) = progress.throughput() {
+        text.push_str(&format!(
+            "  {} · {} elapsed",
+            format_rate(rate.files_per_sec, rate.bytes_per_sec),
+            format_duration(rate.elapsed)
+        ));
+        if let Some(eta) = progress.eta(rate.files_per_sec, running) {
+            text.push_str(&format!(" · ETA {}", format_duration(eta)));
+        }
+    }
+    if paused {
+        text.push_str("  ⏸ Paused");
+    }
+    ui.add(
+        egui::ProgressBar::new(frac)
+            .text(RichText::new(text).size(13.0))
+            .animate(scanning)
+            .desired_width(ui.available_width()),
+    );
+}
+
+fn push_log(log: &Mutex<RunLog>, event: LogEvent) {
+    // Interim scan counts only feed the progress bar.
+    if matches!(event, LogEvent::Discovered { complete: false, .. }) {
+        return;
+    }
+    if let Ok(mut log) = log.lock() {
+        if log.events.len() >= LOG_CAP {
+            let drop = log.events.len() - LOG_TRIM_TO;
+            log.events.drain(0..drop);
+            log.dropped += drop;
+        }
+        log.events.push(event);
     }
 }
 
@@ -413,32 +1619,465 @@ fn format_event(event: &LogEvent) -> (String, Color32) {
             format!("Starting conversion from: {input_dir}"),
             Color32::LIGHT_GRAY,
         ),
-        LogEvent::Discovered { total } => (
+        LogEvent::Discovered { total, .. } => (
             format!("Found {total} files to convert"),
             Color32::LIGHT_GRAY,
         ),
-        LogEvent::Converted { path } => (
-            format!("Converted: {}", path.display()),
-            Color32::from_rgb(150, 220, 150),
+        LogEvent::Watching { input_dir } => (
+            format!("Watching for new images in: {input_dir}"),
+            Color32::LIGHT_GRAY,
         ),
-        LogEvent::Skipped { path, .. } => (
-            format!("Skipped (already exists): {}", path.display()),
+        LogEvent::Paused => (
+            "Paused — files already in progress will finish".to_string(),
             Color32::from_rgb(200, 180, 120),
         ),
-        LogEvent::Error { msg } => (
-            format!("Error: {msg}"),
+        LogEvent::Resumed => ("Resumed".to_string(), Color32::LIGHT_GRAY),
+        LogEvent::Detected { path } => (
+            format!("Detected: {}", path.display()),
+            Color32::LIGHT_GRAY,
+        ),
+        LogEvent::Converted { path, result } => {
+            let mode = if result.lossless {
+                "lossless".to_string()
+            } else {
+                format!("q{}", result.quality.round())
+            };
+            let mut text = format!("Converted: {} ({mode}", path.display());
+            if let Some(scores) = result.scores {
+                text.push_str(&format!(", PSNR {:.1} dB, SSIM {:.4}", scores.psnr, scores.ssim));
+            }
+            text.push(')');
+            if result.below_min_ssim {
+                text.push_str(" ⚠ below min SSIM");
+                (text, Color32::from_rgb(200, 180, 120))
+            } else {
+                (text, Color32::from_rgb(150, 220, 150))
+            }
+        }
+        LogEvent::Skipped { path, reason } => (
+            match reason {
+                SkipReason::OutputExists => format!("Skipped (already exists): {}", path.display()),
+                SkipReason::OutputIsInput => {
+                    format!("Skipped (output would overwrite an input): {}", path.display())
+                }
+                SkipReason::OutputClaimed { by } => format!(
+                    "Skipped (same output as {}): {}",
+                    by.display(),
+                    path.display()
+                ),
+                SkipReason::Filtered { rule } => format!("Skipped ({rule}): {}", path.display()),
+                SkipReason::NotSmaller { source_len, output_len, original } => format!(
+                    "Skipped (WebP {} vs {} source{}): {}",
+                    format_bytes(*output_len),
+                    format_bytes(*source_len),
+                    match original {
+                        OriginalCopy::NotCopied => "",
+                        OriginalCopy::Copied => ", original copied",
+                        OriginalCopy::NameTaken => ", original not copied: name taken",
+                    },
+                    path.display()
+                ),
+                SkipReason::TooLarge { width, height } => format!(
+                    "Skipped ({width}×{height} exceeds the pixel limit): {}",
+                    path.display()
+                ),
+            },
+            Color32::from_rgb(200, 180, 120),
+        ),
+        LogEvent::SourceRemoved { path, moved_to: Some(to) } => (
+            format!("Moved original: {} → {}", path.display(), to.display()),
+            Color32::LIGHT_GRAY,
+        ),
+        LogEvent::SourceRemoved { path, moved_to: None } => (
+            format!("Deleted original: {}", path.display()),
+            Color32::LIGHT_GRAY,
+        ),
+        LogEvent::Error { path: Some(path), msg } => (
+            format!("Error: {}: {msg}", path.display()),
             Color32::from_rgb(240, 120, 120),
         ),
-        LogEvent::Finished { success, skipped, errors, total } => (
-            format!("Finished — Success: {success}, Skipped: {skipped}, Errors: {errors}, Total: {total}"),
-            Color32::LIGHT_BLUE,
+        LogEvent::Error { path: None, msg } => (
+            format!("Error: {msg}"),
+            Color32::from_rgb(240, 120, 120),
         ),
+        LogEvent::Finished { success, skipped, errors, total, elapsed, bytes_read } => {
+            let secs = elapsed.as_secs_f64().max(0.001);
+            let processed = success + skipped + errors;
+            (
+                format!(
+                    "Finished — Success: {success}, Skipped: {skipped}, Errors: {errors}, \
+                     Total: {total} in {} ({})",
+                    format_duration(*elapsed),
+                    format_rate(processed as f64 / secs, *bytes_read as f64 / secs)
+                ),
+                Color32::LIGHT_BLUE,
+            )
+        }
     }
 }
 
-fn is_error_or_summary(event: &LogEvent) -> bool {
-    matches!(
-        event,
-        LogEvent::Error { .. } | LogEvent::Finished { .. } | LogEvent::Discovered { .. }
-    )
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cli::Cli;
+    use settings::JobSettings;
+    use webp_converter_app::Conversion;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|a| a.to_string()).collect()
+    }
+
+    #[test]
+    fn cli_parses_watch_and_rejects_the_rest() {
+        let cli = Cli::parse(&args(&["--watch", "in", "out"])).unwrap();
+        assert_eq!(cli.watch, Some(("in".into(), "out".into())));
+        assert_eq!(cli.preset, None);
+        let cli = Cli::parse(&args(&["--preset", "icons.toml", "--watch", "a", "b"])).unwrap();
+        assert_eq!(cli.preset, Some(PathBuf::from("icons.toml")));
+        assert!(Cli::parse(&args(&["--watch", "in", "out", "--preset"])).is_err());
+        assert!(Cli::parse(&args(&["--watch", "in"])).is_err());
+        assert!(Cli::parse(&args(&["--frobnicate"])).is_err());
+        assert!(Cli::parse(&[]).is_err());
+    }
+
+    #[test]
+    fn settings_round_trip_and_keep_unknown_keys() {
+        let mut settings = Settings {
+            input_path: "in".into(),
+            presets: vec![Preset::from_options("Icons", &ConvertOptions::default())],
+            ..Settings::default()
+        };
+        settings.options.min_savings = Some(0.1);
+        settings.jobs.push(JobSettings {
+            held: true,
+            files: vec![PathBuf::from("a\tb.png")],
+            options: ConvertOptions {
+                source_action: SourceAction::MoveTo(PathBuf::from("archive")),
+                ..ConvertOptions::default()
+            },
+            ..JobSettings::default()
+        });
+        let text = settings.to_storage(&[]).unwrap();
+        assert!(text.contains("min_savings = 0.1\n"), "{text}");
+
+        // As written by a newer version.
+        let newer = format!(
+            "theme = \"dark\"\n{}\n[options.resize]\nwidth = 800\n",
+            text.replace("[log]\n", "[log]\nrotate = true\n")
+        );
+        let (parsed, unknown) = Settings::parse(&newer).unwrap().unwrap();
+        assert_eq!(unknown.len(), 3);
+        assert_eq!(parsed.input_path, "in");
+        assert_eq!(parsed.presets, settings.presets);
+        let job = &parsed.jobs[0];
+        assert!(job.held);
+        assert_eq!(job.files, [PathBuf::from("a\tb.png")]);
+        assert_eq!(job.options.source_action, SourceAction::MoveTo("archive".into()));
+
+        let saved = parsed.to_storage(&unknown).unwrap();
+        let (_, kept) = Settings::parse(&saved).unwrap().unwrap();
+        assert_eq!(kept.len(), 3);
+        assert!(saved.contains("theme = \"dark\"") && saved.contains("rotate = true"));
+    }
+
+    #[test]
+    fn invalid_settings_are_an_error_and_legacy_is_not_toml() {
+        assert!(Settings::parse("version = 1\noptions = 5\n").unwrap().is_err());
+        assert!(Settings::parse("input_path=C:\\in\nquality=80\n").is_none());
+    }
+
+    #[test]
+    fn journal_settings_round_trip_awkward_paths() {
+        let spec = JobSpec {
+            input: "in\tdir".into(),
+            files: vec![PathBuf::from("line\nbreak.png")],
+            output: "out".into(),
+            options: ConvertOptions {
+                source_action: SourceAction::MoveTo("arch\tive".into()),
+                ..ConvertOptions::default()
+            },
+        };
+        let settings = Job::new(0, spec, JobState::Pending).to_journal().unwrap();
+        let (read, state) = Job::from_journal(&settings).unwrap();
+        assert!(state == JobState::Pending);
+        assert_eq!(read.input, "in\tdir");
+        assert_eq!(read.files, [PathBuf::from("line\nbreak.png")]);
+        assert_eq!(read.options.source_action, SourceAction::MoveTo("arch\tive".into()));
+
+        // Journals written before settings were TOML.
+        let mut legacy = vec!["held", "/in", "/out", "75", "false", "false"];
+        legacy.extend(["", "", "", "", "", "", "false", "", "false", "", "false", "delete"]);
+        legacy.extend(["false", "false", "false", "false", "", "", "", "false", "a.png"]);
+        let (read, state) = Job::from_journal(&legacy.join("\t")).unwrap();
+        assert!(state == JobState::Held);
+        assert_eq!((read.input.as_str(), read.options.quality), ("/in", 75.0));
+        assert_eq!(read.options.source_action, SourceAction::Delete);
+        assert_eq!(read.files, [PathBuf::from("a.png")]);
+    }
+
+    #[test]
+    fn oversized_limits_are_clamped_so_settings_still_save() {
+        let mut app = MyApp::default();
+        app.load_from_storage("workers=4000000000\nmax_pixels=18446744073709551615\n");
+        assert_eq!(app.workers, Some(MAX_WORKERS));
+        assert_eq!(app.max_pixels, Some(MAX_MEGAPIXELS * 1_000_000));
+        assert!(app.settings().to_storage(&[]).is_ok());
+    }
+
+    #[test]
+    fn legacy_settings_are_migrated() {
+        let legacy = "input_path=/photos\nquality=70\nsource_action=delete\n\
+                      log_errors_only=true\nlog_format=json\n\
+                      preset=Thumbs\t60\tfalse\tfalse\tfalse\t\tfalse\t5\t\tfalse\n";
+        let mut app = MyApp::default();
+        app.load_from_storage(legacy);
+        assert_eq!(app.input_path, "/photos");
+        assert_eq!(app.quality, 70.0);
+        assert_eq!(app.source_action, SourceAction::Delete);
+        assert!(!app.log_view.show_converted && app.log_view.show_problems);
+        assert!(app.log_format == LogFormat::JsonLines);
+        assert_eq!(app.presets[0].name, "Thumbs");
+        assert_eq!(app.presets[0].min_savings, Some(5.0));
+
+        let saved = app.settings().to_storage(&app.unknown_settings).unwrap();
+        let mut reloaded = MyApp::default();
+        reloaded.load_from_storage(&saved);
+        assert_eq!(reloaded.input_path, "/photos");
+        assert_eq!(reloaded.source_action, SourceAction::Delete);
+        assert!(reloaded.log_format == LogFormat::JsonLines);
+        assert_eq!(reloaded.presets, app.presets);
+    }
+
+    #[test]
+    fn json_log_lines_escape_awkward_values() {
+        let dir = std::env::temp_dir().join(format!("webp_log_file_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let settings = LogFileSettings {
+            dir: dir.clone(),
+            format: LogFormat::JsonLines,
+            keep: 1,
+        };
+        let log = LogFile::create(&settings).unwrap();
+        log.write(&LogEvent::Error {
+            path: Some(PathBuf::from("a\"b\\c\nd\u{1}.png")),
+            msg: "tab\there".into(),
+        });
+        drop(log);
+
+        let logs: Vec<_> = std::fs::read_dir(&dir).unwrap().flatten().collect();
+        assert_eq!(logs.len(), 1);
+        let text = std::fs::read_to_string(logs[0].path()).unwrap();
+        assert_eq!(text.lines().count(), 1);
+        let expected = r#""event":"error","path":"a\"b\\c\nd\u0001.png","message":"tab\there"}"#;
+        assert!(text.trim_end().ends_with(expected), "{text}");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn savings_count_written_outputs_and_bucket_every_file() {
+        let mut savings = Savings::default();
+        savings.record(&converted("a.png", 1000, 250));
+        savings.record(&converted("b.png", 1000, 1500));
+        savings.record(&converted("empty.png", 0, 10));
+        savings.record(&LogEvent::Skipped {
+            path: "c.png".into(),
+            reason: SkipReason::NotSmaller {
+                source_len: 1000,
+                output_len: 950,
+                original: OriginalCopy::NotCopied,
+            },
+        });
+
+        // The discarded WebP saved nothing, so it is left out of the totals.
+        let (input, output, saved) = savings.totals();
+        assert_eq!((input, output), (2000, 1760));
+        assert!((saved - 12.0).abs() < 1e-9);
+        // 25%, 95%, and two at or above the source size.
+        let mut expected = [0; 11];
+        expected[2] = 1;
+        expected[9] = 1;
+        expected[10] = 2;
+        assert_eq!(savings.histogram(), expected);
+        assert_eq!(Savings::default().totals(), (0, 0, 0.0));
+    }
+
+    #[derive(Default)]
+    struct MemoryStorage(std::collections::HashMap<String, String>);
+
+    impl eframe::Storage for MemoryStorage {
+        fn get_string(&self, key: &str) -> Option<String> {
+            self.0.get(key).cloned()
+        }
+
+        fn set_string(&mut self, key: &str, value: String) {
+            self.0.insert(key.to_string(), value);
+        }
+
+        fn flush(&mut self) {}
+    }
+
+    #[test]
+    fn unreadable_settings_are_never_overwritten() {
+        let text = Settings::default().to_storage(&[]).unwrap();
+        let bad_value = text.replace("quality = 87.0", "quality = \"high\"");
+        let newer = text.replace("version = 1", "version = 2");
+        assert!(Settings::parse(&bad_value).unwrap().is_err());
+        assert!(Settings::parse(&newer).unwrap().is_err());
+        for raw in [bad_value, newer] {
+            let mut app = MyApp::default();
+            app.load_from_storage(&raw);
+            app.quality = 10.0;
+            let mut storage = MemoryStorage::default();
+            eframe::App::save(&mut app, &mut storage);
+            assert_eq!(storage.0.get(STORAGE_KEY), Some(&raw));
+        }
+    }
+
+    fn converted(name: &str, source_len: u64, output_len: u64) -> LogEvent {
+        LogEvent::Converted {
+            path: name.into(),
+            result: Conversion {
+                lossless: false,
+                quality: 75.0,
+                scores: None,
+                below_min_ssim: false,
+                source_len,
+                output_len,
+            },
+        }
+    }
+
+    #[test]
+    fn throughput_only_counts_files_inside_the_rate_window() {
+        let progress = Progress::default();
+        progress.record(&LogEvent::Started {
+            input_dir: "in".into(),
+        });
+        let now = Instant::now();
+        {
+            let mut timing = progress.timing.lock().unwrap();
+            timing.started = now.checked_sub(Duration::from_secs(60));
+            timing
+                .recent
+                .push_back((now - Duration::from_secs(30), 1 << 20));
+        }
+        progress.record(&converted("a.png", 2048, 1024));
+        assert_eq!(progress.timing.lock().unwrap().recent.len(), 1);
+        assert_eq!(progress.done.load(Ordering::Relaxed), 1);
+
+        let rate = progress.throughput().unwrap();
+        assert!(rate.elapsed >= Duration::from_secs(60));
+        // One file of 2 KiB over the ten-second window.
+        assert!((rate.files_per_sec - 0.1).abs() < 0.01);
+        assert!((rate.bytes_per_sec - 204.8).abs() < 20.0);
+
+        progress.record(&LogEvent::Finished {
+            success: 1,
+            skipped: 0,
+            errors: 0,
+            total: 1,
+            elapsed: Duration::from_secs(60),
+            bytes_read: 2048,
+        });
+        let frozen = progress.throughput().unwrap().elapsed;
+        assert_eq!(progress.throughput().unwrap().elapsed, frozen);
+    }
+
+    #[test]
+    fn eta_waits_for_a_known_total_and_a_moving_run() {
+        let progress = Progress::default();
+        progress.record(&LogEvent::Started {
+            input_dir: "in".into(),
+        });
+        progress.record(&LogEvent::Discovered {
+            found: 3,
+            total: 3,
+            complete: false,
+        });
+        assert_eq!(progress.eta(1.0, true), None, "still scanning");
+        progress.record(&LogEvent::Discovered {
+            found: 0,
+            total: 3,
+            complete: true,
+        });
+        progress.record(&converted("a.png", 10, 5));
+        assert_eq!(progress.eta(1.0, true), Some(Duration::from_secs(2)));
+        assert_eq!(progress.eta(0.5, true), Some(Duration::from_secs(4)));
+        assert_eq!(progress.eta(0.0, true), None);
+        assert_eq!(progress.eta(1.0, false), None);
+
+        progress.record(&LogEvent::Paused);
+        assert_eq!(progress.eta(1.0, true), None, "paused");
+        progress.record(&LogEvent::Resumed);
+        progress.record(&converted("b.png", 10, 5));
+        progress.record(&converted("c.png", 10, 5));
+        assert_eq!(progress.eta(1.0, true), None, "all done");
+    }
+
+    #[test]
+    fn durations_switch_to_hours_from_an_hour_up() {
+        let format = |secs| format_duration(Duration::from_secs(secs));
+        assert_eq!(format(0), "0:00");
+        assert_eq!(format(59), "0:59");
+        assert_eq!(format(61), "1:01");
+        assert_eq!(format(3599), "59:59");
+        assert_eq!(format(3600), "1:00:00");
+        assert_eq!(format(3723), "1:02:03");
+        assert_eq!(format(36_000), "10:00:00");
+        assert_eq!(format_duration(Duration::from_millis(1999)), "0:01");
+    }
+
+    #[test]
+    fn log_view_follows_pushes_trims_and_clears() {
+        let log = Mutex::new(RunLog::default());
+        let mut view = LogView::default();
+        let started = LogEvent::Started {
+            input_dir: "in".into(),
+        };
+        let skipped = LogEvent::Skipped {
+            path: "b.png".into(),
+            reason: SkipReason::OutputExists,
+        };
+        let error = LogEvent::Error {
+            path: Some("c.png".into()),
+            msg: "broken".into(),
+        };
+        for event in [started, converted("a.png", 10, 5), skipped, error] {
+            push_log(&log, event);
+        }
+        view.refresh(&log.lock().unwrap());
+        assert_eq!(view.rows(), [0, 1, 2, 3]);
+        assert_eq!(view.counts(), [1, 1, 1]);
+
+        view.show_skipped = false;
+        view.refresh(&log.lock().unwrap());
+        assert_eq!(view.rows(), [0, 1, 3]);
+        view.search = " C.PNG ".into();
+        view.refresh(&log.lock().unwrap());
+        assert_eq!(view.rows(), [3]);
+        view.search.clear();
+        view.show_skipped = true;
+
+        // Filling the log to the cap trims its oldest events on the next push.
+        for i in 4..LOG_CAP {
+            push_log(&log, converted(&format!("{i}.png"), 10, 5));
+        }
+        view.refresh(&log.lock().unwrap());
+        assert_eq!(view.rows().len(), LOG_CAP);
+        push_log(&log, converted("last.png", 10, 5));
+        view.refresh(&log.lock().unwrap());
+        let trimmed = LOG_CAP - LOG_TRIM_TO;
+        assert_eq!(log.lock().unwrap().dropped, trimmed);
+        assert_eq!(view.rows(), (0..=LOG_TRIM_TO).collect::<Vec<_>>());
+        assert_eq!(view.counts(), [LOG_TRIM_TO + 1, 0, 0]);
+
+        log.lock().unwrap().clear();
+        view.refresh(&log.lock().unwrap());
+        assert!(view.rows().is_empty());
+        assert_eq!(view.counts(), [0, 0, 0]);
+        push_log(&log, converted("again.png", 10, 5));
+        view.refresh(&log.lock().unwrap());
+        assert_eq!(view.rows(), [0]);
+        assert_eq!(view.counts(), [1, 0, 0]);
+    }
 }
@@ -0,0 +1,97 @@
+use crate::RgbaImage;
+use anyhow::{Result, ensure};
+
+/// Side of the square SSIM window and the step between windows.
+const SSIM_WINDOW: usize = 8;
+const SSIM_STRIDE: usize = 4;
+
+/// Fidelity of an encoded image against its source.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct QualityScores {
+    /// Peak signal-to-noise ratio in dB; infinite for identical images.
+    pub psnr: f64,
+    /// Mean structural similarity of the luma channel, 1.0 for identical.
+    pub ssim: f64,
+}
+
+/// Scores `encoded` against `source`. Both are compared with colour
+/// premultiplied by alpha, so differences hidden under fully transparent
+/// pixels do not count.
+pub fn compare_images(source: &RgbaImage, encoded: &RgbaImage) -> Result<QualityScores> {
+    ensure!(
+        (source.width, source.height) == (encoded.width, encoded.height),
+        "Images differ in size."
+    );
+    ensure!(source.width > 0 && source.height > 0, "Image is empty.");
+    Ok(QualityScores {
+        psnr: psnr(source, encoded),
+        ssim: ssim(source, encoded),
+    })
+}
+
+fn premultiplied(px: &[u8]) -> [f64; 3] {
+    let a = px[3] as f64 / 255.0;
+    [px[0] as f64 * a, px[1] as f64 * a, px[2] as f64 * a]
+}
+
+fn psnr(a: &RgbaImage, b: &RgbaImage) -> f64 {
+    let mut sum = 0.0;
+    for (pa, pb) in a.pixels.chunks_exact(4).zip(b.pixels.chunks_exact(4)) {
+        let (ca, cb) = (premultiplied(pa), premultiplied(pb));
+        sum += (0..3).map(|i| (ca[i] - cb[i]).powi(2)).sum::<f64>();
+    }
+    let mse = sum / (a.pixels.len() / 4 * 3) as f64;
+    if mse == 0.0 {
+        f64::INFINITY
+    } else {
+        10.0 * (255.0 * 255.0 / mse).log10()
+    }
+}
+
+fn luma(image: &RgbaImage) -> Vec<f64> {
+    image
+        .pixels
+        .chunks_exact(4)
+        .map(|px| {
+            let [r, g, b] = premultiplied(px);
+            0.299 * r + 0.587 * g + 0.114 * b
+        })
+        .collect()
+}
+
+fn ssim(a: &RgbaImage, b: &RgbaImage) -> f64 {
+    const C1: f64 = (0.01 * 255.0) * (0.01 * 255.0);
+    const C2: f64 = (0.03 * 255.0) * (0.03 * 255.0);
+
+    let (w, h) = (a.width as usize, a.height as usize);
+    let (ya, yb) = (luma(a), luma(b));
+    let win_w = SSIM_WINDOW.min(w);
+    let win_h = SSIM_WINDOW.min(h);
+
+    let mut total = 0.0;
+    let mut windows = 0;
+    for y0 in (0..=h - win_h).step_by(SSIM_STRIDE) {
+        for x0 in (0..=w - win_w).step_by(SSIM_STRIDE) {
+            let n = (win_w * win_h) as f64;
+            let (mut sa, mut sb, mut saa, mut sbb, mut sab) = (0.0, 0.0, 0.0, 0.0, 0.0);
+            for y in y0..y0 + win_h {
+                for x in x0..x0 + win_w {
+                    let (va, vb) = (ya[y * w + x], yb[y * w + x]);
+                    sa += va;
+                    sb += vb;
+                    saa += va * va;
+                    sbb += vb * vb;
+                    sab += va * vb;
+                }
+            }
+            let (ma, mb) = (sa / n, sb / n);
+            let var_a = saa / n - ma * ma;
+            let var_b = sbb / n - mb * mb;
+            let cov = sab / n - ma * mb;
+            total += ((2.0 * ma * mb + C1) * (2.0 * cov + C2))
+                / ((ma * ma + mb * mb + C1) * (var_a + var_b + C2));
+            windows += 1;
+        }
+    }
+    total / windows as f64
+}
@@ -0,0 +1,87 @@
+use crate::ConvertOptions;
+use anyhow::{Context, Result, ensure};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+/// A named bundle of encoding settings, such as "Thumbnails" at quality 70
+/// or lossless "Icons". Paths, filters and what happens to sources are not
+/// part of a preset.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Preset {
+    pub name: String,
+    #[serde(serialize_with = "crate::serialize_f32")]
+    pub quality: f32,
+    pub lossless: bool,
+    pub auto_lossless: bool,
+    pub measure_quality: bool,
+    pub min_ssim: Option<f64>,
+    pub raise_quality: bool,
+    #[serde(serialize_with = "crate::serialize_opt_f32")]
+    pub min_savings: Option<f32>,
+    pub max_pixels: Option<u64>,
+    pub downscale_large: bool,
+}
+
+impl Default for Preset {
+    fn default() -> Self {
+        Self::from_options("", &ConvertOptions::default())
+    }
+}
+
+impl Preset {
+    /// Captures the encoding settings of `options` under `name`.
+    pub fn from_options(name: &str, options: &ConvertOptions) -> Self {
+        Self {
+            name: name.to_string(),
+            quality: options.quality,
+            lossless: options.lossless,
+            auto_lossless: options.auto_lossless,
+            measure_quality: options.measure_quality,
+            min_ssim: options.min_ssim,
+            raise_quality: options.raise_quality,
+            min_savings: options.min_savings,
+            max_pixels: options.max_pixels,
+            downscale_large: options.downscale_large,
+        }
+    }
+
+    /// Overwrites the encoding settings of `options`, leaving the rest.
+    pub fn apply(&self, options: &mut ConvertOptions) {
+        options.quality = self.quality;
+        options.lossless = self.lossless;
+        options.auto_lossless = self.auto_lossless;
+        options.measure_quality = self.measure_quality;
+        options.min_ssim = self.min_ssim;
+        options.raise_quality = self.raise_quality;
+        options.min_savings = self.min_savings;
+        options.max_pixels = self.max_pixels;
+        options.downscale_large = self.downscale_large;
+    }
+
+    /// Serializes to TOML; unset limits are left out.
+    pub fn to_toml(&self) -> Result<String> {
+        toml::to_string(self).context("Failed to serialize preset")
+    }
+
+    /// Parses a preset written by [`Preset::to_toml`] or by hand. Missing
+    /// keys take their defaults; `name` is required.
+    pub fn from_toml(text: &str) -> Result<Self> {
+        let mut preset: Self = toml::from_str(text)?;
+        preset.name = preset.name.trim().to_string();
+        ensure!(!preset.name.is_empty(), "Preset has no name");
+        preset.quality = preset.quality.clamp(0.0, 100.0);
+        Ok(preset)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        fs::write(path, self.to_toml()?).with_context(|| format!("Failed to write {:?}", path))
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let text =
+            fs::read_to_string(path).with_context(|| format!("Failed to read {:?}", path))?;
+        Self::from_toml(&text).with_context(|| format!("Invalid preset {:?}", path))
+    }
+}
@@ -0,0 +1,195 @@
+//! The preset panel: named encoding presets saved with the app state and
+//! shared as TOML files.
+
+use crate::MyApp;
+use eframe::egui::{self, Color32, RichText};
+use std::sync::atomic::Ordering;
+use std::sync::{Arc, Mutex};
+use webp_converter_app::Preset;
+
+/// Outcome of an import or export dialog, picked up on the next frame.
+pub type PresetDialogResult = Arc<Mutex<Option<Result<Option<Preset>, String>>>>;
+
+/// Reads a `preset=` line of the legacy settings format.
+pub fn preset_from_storage(value: &str) -> Option<Preset> {
+    let mut fields = value.split('\t');
+    Some(Preset {
+        name: fields.next()?.to_string(),
+        quality: fields.next()?.parse::<f32>().ok()?.clamp(0.0, 100.0),
+        lossless: fields.next()? == "true",
+        auto_lossless: fields.next()? == "true",
+        measure_quality: fields.next()? == "true",
+        min_ssim: fields.next()?.parse().ok(),
+        raise_quality: fields.next()? == "true",
+        min_savings: fields.next()?.parse().ok(),
+        max_pixels: fields.next()?.parse().ok(),
+        downscale_large: fields.next()? == "true",
+    })
+}
+
+impl MyApp {
+    fn apply_preset(&mut self, preset: &Preset) {
+        let mut options = self.options();
+        preset.apply(&mut options);
+        self.set_options(options);
+    }
+
+    /// Adds `preset`, replacing any preset of the same name, and selects it.
+    fn store_preset(&mut self, preset: Preset) {
+        self.preset_name = preset.name.clone();
+        match self.presets.iter().position(|p| p.name == preset.name) {
+            Some(i) => {
+                self.presets[i] = preset;
+                self.selected_preset = Some(i);
+            }
+            None => {
+                self.presets.push(preset);
+                self.selected_preset = Some(self.presets.len() - 1);
+            }
+        }
+    }
+
+    fn drain_preset_dialog(&mut self) {
+        let result = self.preset_dialog.lock().ok().and_then(|mut r| r.take());
+        match result {
+            Some(Ok(Some(preset))) => {
+                self.preset_status = Some(format!("Imported \"{}\"", preset.name));
+                self.store_preset(preset);
+            }
+            Some(Ok(None)) => self.preset_status = Some("Exported".into()),
+            Some(Err(msg)) => self.preset_status = Some(msg),
+            None => {}
+        }
+    }
+
+    pub(crate) fn ui_presets(&mut self, ui: &mut egui::Ui, ctx: &egui::Context) {
+        self.drain_preset_dialog();
+        let running = self.is_running.load(Ordering::Relaxed);
+        egui::CollapsingHeader::new(RichText::new("Presets").size(14.0))
+            .id_salt("presets")
+            .show(ui, |ui| {
+                ui.add_enabled_ui(!running, |ui| {
+                    ui.horizontal_wrapped(|ui| {
+                        let selected = self.selected_preset.and_then(|i| self.presets.get(i));
+                        let text = selected.map_or("Choose…", |p| p.name.as_str()).to_string();
+                        egui::ComboBox::from_id_salt("preset")
+                            .selected_text(text)
+                            .show_ui(ui, |ui| {
+                                for (i, preset) in self.presets.iter().enumerate() {
+                                    let on = self.selected_preset == Some(i);
+                                    if ui.selectable_label(on, &preset.name).clicked() {
+                                        self.selected_preset = Some(i);
+                                        self.preset_name = preset.name.clone();
+                                    }
+                                }
+                            });
+                        ui.add(
+                            egui::TextEdit::singleline(&mut self.preset_name)
+                                .hint_text("Preset name")
+                                .desired_width(120.0),
+                        );
+                        self.ui_preset_buttons(ui, ctx);
+                    });
+                    if let Some(status) = &self.preset_status {
+                        ui.label(RichText::new(status).size(12.0).color(Color32::GRAY));
+                    }
+                });
+            });
+    }
+
+    /// Save and Rename use the name typed in the text field; the others act
+    /// on the preset chosen in the combo box.
+    fn ui_preset_buttons(&mut self, ui: &mut egui::Ui, ctx: &egui::Context) {
+        let name = self.preset_name.trim().to_string();
+        let selected = self.selected_preset.filter(|&i| i < self.presets.len());
+        let name_taken = self.presets.iter().any(|p| p.name == name);
+
+        if ui
+            .add_enabled(selected.is_some(), egui::Button::new("Apply"))
+            .clicked()
+            && let Some(i) = selected
+        {
+            let preset = self.presets[i].clone();
+            self.apply_preset(&preset);
+            self.preset_status = Some(format!("Applied \"{}\"", preset.name));
+        }
+        let save = ui
+            .add_enabled(!name.is_empty(), egui::Button::new("Save"))
+            .on_hover_text("Save the current encoding settings under this name");
+        if save.clicked() {
+            self.store_preset(Preset::from_options(&name, &self.options()));
+            self.preset_status = Some(format!("Saved \"{name}\""));
+        }
+        let rename = ui
+            .add_enabled(
+                selected.is_some() && !name.is_empty() && !name_taken,
+                egui::Button::new("Rename"),
+            )
+            .on_hover_text("Give the chosen preset the name typed here");
+        if rename.clicked()
+            && let Some(i) = selected
+        {
+            let old = std::mem::replace(&mut self.presets[i].name, name.clone());
+            self.preset_status = Some(format!("Renamed \"{old}\" to \"{name}\""));
+        }
+        if ui
+            .add_enabled(selected.is_some(), egui::Button::new("Delete"))
+            .clicked()
+            && let Some(i) = selected
+        {
+            let removed = self.presets.remove(i);
+            self.selected_preset = None;
+            self.preset_status = Some(format!("Deleted \"{}\"", removed.name));
+        }
+        if ui
+            .add_enabled(selected.is_some(), egui::Button::new("Export…"))
+            .clicked()
+            && let Some(i) = selected
+        {
+            self.spawn_preset_export(self.presets[i].clone(), ctx);
+        }
+        if ui.button("Import…").clicked() {
+            self.spawn_preset_import(ctx);
+        }
+    }
+
+    fn spawn_preset_export(&self, preset: Preset, ctx: &egui::Context) {
+        let slot = self.preset_dialog.clone();
+        let ctx = ctx.clone();
+        std::thread::spawn(move || {
+            let Some(path) = rfd::FileDialog::new()
+                .add_filter("TOML", &["toml"])
+                .set_file_name(format!("{}.toml", preset.name))
+                .save_file()
+            else {
+                return;
+            };
+            let result = preset
+                .save(&path)
+                .map(|()| None)
+                .map_err(|e| format!("{e:#}"));
+            if let Ok(mut s) = slot.lock() {
+                *s = Some(result);
+            }
+            ctx.request_repaint();
+        });
+    }
+
+    fn spawn_preset_import(&self, ctx: &egui::Context) {
+        let slot = self.preset_dialog.clone();
+        let ctx = ctx.clone();
+        std::thread::spawn(move || {
+            let Some(path) = rfd::FileDialog::new()
+                .add_filter("TOML", &["toml"])
+                .pick_file()
+            else {
+                return;
+            };
+            let result = Preset::load(&path).map(Some).map_err(|e| format!("{e:#}"));
+            if let Ok(mut s) = slot.lock() {
+                *s = Some(result);
+            }
+            ctx.request_repaint();
+        });
+    }
+}
@@ -0,0 +1,329 @@
+//! Before/after preview of a single image at the current encoding settings.
+
+use crate::{MyApp, format_bytes};
+use eframe::egui::{self, Color32, ColorImage, Rect, RichText, TextureHandle, Vec2};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use webp_converter_app::{RgbaImage, SUPPORTED_EXTENSIONS, decode_webp, encode_webp, load_rgba};
+
+#[derive(Clone, Copy, PartialEq)]
+struct EncodeSettings {
+    quality: f32,
+    lossless: bool,
+}
+
+/// Result of one background encode, tagged with the source it belongs to.
+struct Encoded {
+    source: PathBuf,
+    /// Set only when the source had to be decoded for this request.
+    original: Option<Arc<RgbaImage>>,
+    webp: Result<(RgbaImage, usize), String>,
+}
+
+pub struct Preview {
+    pub open: bool,
+    source: Option<PathBuf>,
+    source_len: u64,
+    /// Decoded source, kept so slider changes only re-encode.
+    original: Option<Arc<RgbaImage>>,
+    original_tex: Option<TextureHandle>,
+    webp_tex: Option<TextureHandle>,
+    webp_len: Option<usize>,
+    error: Option<String>,
+    /// Last source/settings pair sent to the worker.
+    requested: Option<(PathBuf, EncodeSettings)>,
+    busy: bool,
+    result: Arc<Mutex<Option<Encoded>>>,
+    picked: Arc<Mutex<Option<PathBuf>>>,
+    split_view: bool,
+    split: f32,
+    zoom: f32,
+    pan: Vec2,
+}
+
+impl Default for Preview {
+    fn default() -> Self {
+        Self {
+            open: false,
+            source: None,
+            source_len: 0,
+            original: None,
+            original_tex: None,
+            webp_tex: None,
+            webp_len: None,
+            error: None,
+            requested: None,
+            busy: false,
+            result: Arc::new(Mutex::new(None)),
+            picked: Arc::new(Mutex::new(None)),
+            split_view: true,
+            split: 0.5,
+            zoom: 1.0,
+            pan: Vec2::ZERO,
+        }
+    }
+}
+
+impl Preview {
+    fn set_source(&mut self, path: PathBuf) {
+        self.source_len = std::fs::metadata(&path).map_or(0, |m| m.len());
+        self.source = Some(path);
+        self.original = None;
+        self.original_tex = None;
+        self.webp_tex = None;
+        self.webp_len = None;
+        self.error = None;
+        self.requested = None;
+        self.zoom = 1.0;
+        self.pan = Vec2::ZERO;
+    }
+
+    fn receive(&mut self, ctx: &egui::Context) {
+        let Some(encoded) = self.result.lock().ok().and_then(|mut r| r.take()) else {
+            return;
+        };
+        self.busy = false;
+        if self.source.as_ref() != Some(&encoded.source) {
+            return;
+        }
+        if let Some(original) = encoded.original {
+            self.original_tex = Some(load_texture(ctx, "preview_original", &original));
+            self.original = Some(original);
+        }
+        match encoded.webp {
+            Ok((image, len)) => {
+                self.webp_tex = Some(load_texture(ctx, "preview_webp", &image));
+                self.webp_len = Some(len);
+                self.error = None;
+            }
+            Err(msg) => self.error = Some(msg),
+        }
+    }
+
+    /// Starts a background encode if the settings moved since the last one.
+    /// Only one encode runs at a time; slider drags coalesce into the next.
+    fn request(&mut self, ctx: &egui::Context, settings: EncodeSettings) {
+        let Some(source) = self.source.clone() else {
+            return;
+        };
+        let wanted = Some((source.clone(), settings));
+        if self.busy || self.requested == wanted {
+            return;
+        }
+        self.requested = wanted;
+        self.busy = true;
+
+        let original = self.original.clone();
+        let slot = self.result.clone();
+        let ctx = ctx.clone();
+        std::thread::spawn(move || {
+            let loaded = original.is_none();
+            let original = match original {
+                Some(image) => Ok(image),
+                None => load_rgba(&source).map(Arc::new),
+            };
+            let encoded = match original {
+                Ok(image) => {
+                    let bytes = encode_webp(&image, settings.quality, settings.lossless);
+                    let webp = decode_webp(&bytes)
+                        .map(|decoded| (decoded, bytes.len()))
+                        .map_err(|e| format!("{e:#}"));
+                    Encoded {
+                        source,
+                        original: loaded.then_some(image),
+                        webp,
+                    }
+                }
+                Err(e) => Encoded {
+                    source,
+                    original: None,
+                    webp: Err(format!("{e:#}")),
+                },
+            };
+            if let Ok(mut slot) = slot.lock() {
+                *slot = Some(encoded);
+            }
+            ctx.request_repaint();
+        });
+    }
+}
+
+fn load_texture(ctx: &egui::Context, name: &str, image: &RgbaImage) -> TextureHandle {
+    let size = [image.width as usize, image.height as usize];
+    let color = ColorImage::from_rgba_unmultiplied(size, &image.pixels);
+    ctx.load_texture(name, color, egui::TextureOptions::LINEAR)
+}
+
+impl MyApp {
+    fn spawn_preview_picker(&self, ctx: &egui::Context) {
+        let slot = self.preview.picked.clone();
+        let ctx = ctx.clone();
+        std::thread::spawn(move || {
+            if let Some(path) = rfd::FileDialog::new()
+                .add_filter("Images", SUPPORTED_EXTENSIONS)
+                .pick_file()
+            {
+                if let Ok(mut s) = slot.lock() {
+                    *s = Some(path);
+                }
+                ctx.request_repaint();
+            }
+        });
+    }
+
+    pub(crate) fn ui_preview(&mut self, ctx: &egui::Context) {
+        if !self.preview.open {
+            return;
+        }
+        let picked = self.preview.picked.lock().ok().and_then(|mut s| s.take());
+        if let Some(path) = picked {
+            self.preview.set_source(path);
+        }
+        if self.preview.source.is_none()
+            && let Some(first) = self.files.first()
+        {
+            self.preview.set_source(first.clone());
+        }
+        self.preview.receive(ctx);
+        let settings = EncodeSettings {
+            quality: self.quality,
+            lossless: self.lossless,
+        };
+        self.preview.request(ctx, settings);
+
+        let mut open = self.preview.open;
+        egui::Window::new("Preview")
+            .open(&mut open)
+            .default_size([640.0, 460.0])
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    if ui.button("Choose image…").clicked() {
+                        self.spawn_preview_picker(ctx);
+                    }
+                    ui.selectable_value(&mut self.preview.split_view, true, "Split");
+                    ui.selectable_value(&mut self.preview.split_view, false, "Side by side");
+                    if ui.button("Reset view").clicked() {
+                        self.preview.zoom = 1.0;
+                        self.preview.pan = Vec2::ZERO;
+                    }
+                    if self.preview.busy {
+                        ui.spinner();
+                    }
+                });
+                if self.preview.split_view {
+                    ui.add(
+                        egui::Slider::new(&mut self.preview.split, 0.0..=1.0)
+                            .show_value(false)
+                            .text("Split"),
+                    );
+                }
+                ui.label(RichText::new(preview_stats(&self.preview)).size(13.0));
+                if let Some(err) = &self.preview.error {
+                    ui.colored_label(Color32::from_rgb(240, 120, 120), err);
+                }
+                image_view(ui, &mut self.preview);
+            });
+        self.preview.open = open;
+    }
+}
+
+fn preview_stats(preview: &Preview) -> String {
+    let Some(source) = &preview.source else {
+        return "Choose an image to preview.".into();
+    };
+    let name = source.file_name().unwrap_or_default().to_string_lossy();
+    match preview.webp_len {
+        Some(len) if preview.source_len > 0 => {
+            let ratio = len as f64 / preview.source_len as f64;
+            format!(
+                "{name}: {} → {} ({:.1}% of original, {:.2}:1)",
+                format_bytes(preview.source_len),
+                format_bytes(len as u64),
+                ratio * 100.0,
+                1.0 / ratio.max(f64::EPSILON),
+            )
+        }
+        _ => format!("{name}: {} → encoding…", format_bytes(preview.source_len)),
+    }
+}
+
+/// Draws both images with shared zoom and pan: drag to pan, scroll to zoom,
+/// double-click to reset.
+fn image_view(ui: &mut egui::Ui, preview: &mut Preview) {
+    let (rect, response) =
+        ui.allocate_exact_size(ui.available_size(), egui::Sense::click_and_drag());
+    if response.dragged() {
+        preview.pan += response.drag_delta();
+    }
+    if response.hovered() {
+        let scroll = ui.input(|i| i.smooth_scroll_delta.y);
+        if scroll != 0.0 {
+            preview.zoom = (preview.zoom * (scroll * 0.002).exp()).clamp(0.1, 32.0);
+        }
+    }
+    if response.double_clicked() {
+        preview.zoom = 1.0;
+        preview.pan = Vec2::ZERO;
+    }
+
+    let painter = ui.painter_at(rect);
+    painter.rect_filled(rect, 0.0, Color32::from_gray(24));
+    let Some(original) = &preview.original_tex else {
+        return;
+    };
+    let image_size = original.size_vec2();
+    let uv = Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0));
+    let placed = |area: Rect| {
+        let fit = (area.width() / image_size.x).min(area.height() / image_size.y);
+        Rect::from_center_size(area.center() + preview.pan, image_size * fit * preview.zoom)
+    };
+
+    if preview.split_view {
+        let image_rect = placed(rect);
+        painter.image(original.id(), image_rect, uv, Color32::WHITE);
+        let split_x = rect.left() + rect.width() * preview.split;
+        if let Some(webp) = &preview.webp_tex {
+            let right = Rect::from_min_max(egui::pos2(split_x, rect.top()), rect.max);
+            painter
+                .with_clip_rect(right)
+                .image(webp.id(), image_rect, uv, Color32::WHITE);
+        }
+        painter.vline(
+            split_x,
+            rect.y_range(),
+            egui::Stroke::new(1.5, Color32::LIGHT_BLUE),
+        );
+    } else {
+        let half = rect.width() / 2.0;
+        let left = Rect::from_min_size(rect.min, egui::vec2(half - 2.0, rect.height()));
+        let right = Rect::from_min_size(
+            egui::pos2(rect.left() + half + 2.0, rect.top()),
+            egui::vec2(half - 2.0, rect.height()),
+        );
+        painter
+            .with_clip_rect(left)
+            .image(original.id(), placed(left), uv, Color32::WHITE);
+        if let Some(webp) = &preview.webp_tex {
+            painter
+                .with_clip_rect(right)
+                .image(webp.id(), placed(right), uv, Color32::WHITE);
+        }
+    }
+
+    let label = |pos, align, text: &str| {
+        painter.text(
+            pos,
+            align,
+            text,
+            egui::FontId::proportional(13.0),
+            Color32::WHITE,
+        );
+    };
+    let pad = egui::vec2(6.0, 4.0);
+    label(rect.left_top() + pad, egui::Align2::LEFT_TOP, "Original");
+    label(
+        rect.right_top() + egui::vec2(-pad.x, pad.y),
+        egui::Align2::RIGHT_TOP,
+        "WebP",
+    );
+}
@@ -0,0 +1,195 @@
+//! How much a run saved, built up from its conversion events.
+
+use crate::format_bytes;
+use eframe::egui::{self, Align2, Color32, FontId, RichText, Sense, Vec2};
+use std::path::PathBuf;
+use webp_converter_app::{LogEvent, SkipReason};
+
+/// Files listed under the largest wins and worst regressions.
+const TOP_N: usize = 5;
+/// Histogram buckets of output size as a share of the source, 10% each;
+/// the last one holds every output at least as large as its source.
+const BUCKETS: usize = 11;
+
+struct FileSavings {
+    path: PathBuf,
+    source_len: u64,
+    output_len: u64,
+    /// The WebP was discarded by the minimum-savings check.
+    discarded: bool,
+}
+
+impl FileSavings {
+    fn saved(&self) -> i64 {
+        self.source_len as i64 - self.output_len as i64
+    }
+
+    fn ratio(&self) -> f64 {
+        self.output_len as f64 / self.source_len.max(1) as f64
+    }
+}
+
+#[derive(Default)]
+pub struct Savings {
+    files: Vec<FileSavings>,
+}
+
+impl Savings {
+    pub fn clear(&mut self) {
+        self.files.clear();
+    }
+
+    pub fn record(&mut self, event: &LogEvent) {
+        let file = match event {
+            LogEvent::Converted { path, result } => FileSavings {
+                path: path.clone(),
+                source_len: result.source_len,
+                output_len: result.output_len,
+                discarded: false,
+            },
+            LogEvent::Skipped {
+                path,
+                reason:
+                    SkipReason::NotSmaller {
+                        source_len,
+                        output_len,
+                        ..
+                    },
+            } => FileSavings {
+                path: path.clone(),
+                source_len: *source_len,
+                output_len: *output_len,
+                discarded: true,
+            },
+            _ => return,
+        };
+        self.files.push(file);
+    }
+
+    pub fn show(&self, ui: &mut egui::Ui) {
+        if self.files.is_empty() {
+            return;
+        }
+        egui::CollapsingHeader::new(RichText::new("Savings").size(14.0))
+            .id_salt("savings")
+            .default_open(true)
+            .show(ui, |ui| {
+                self.ui_totals(ui);
+                ui.columns(2, |columns| {
+                    self.ui_top(&mut columns[0], "Largest wins", true);
+                    self.ui_top(&mut columns[1], "Worst regressions", false);
+                });
+                self.ui_histogram(ui);
+            });
+    }
+
+    /// Bytes in and out, and the percentage saved. Totals cover written
+    /// outputs only; discarded WebPs changed nothing.
+    pub(crate) fn totals(&self) -> (u64, u64, f64) {
+        let written = self.files.iter().filter(|f| !f.discarded);
+        let (input, output) =
+            written.fold((0, 0), |(i, o), f| (i + f.source_len, o + f.output_len));
+        let saved = if input == 0 {
+            0.0
+        } else {
+            (1.0 - output as f64 / input as f64) * 100.0
+        };
+        (input, output, saved)
+    }
+
+    /// Files per histogram bucket, discarded outputs included.
+    pub(crate) fn histogram(&self) -> [usize; BUCKETS] {
+        let mut counts = [0usize; BUCKETS];
+        for file in &self.files {
+            counts[((file.ratio() * 10.0) as usize).min(BUCKETS - 1)] += 1;
+        }
+        counts
+    }
+
+    fn ui_totals(&self, ui: &mut egui::Ui) {
+        let (input, output, saved) = self.totals();
+        let discarded = self.files.iter().filter(|f| f.discarded).count();
+        let mut text = format!(
+            "{} in → {} out, {saved:.1}% saved",
+            format_bytes(input),
+            format_bytes(output)
+        );
+        if discarded > 0 {
+            text.push_str(&format!(" · {discarded} kept as originals"));
+        }
+        ui.label(RichText::new(text).size(13.0).strong());
+    }
+
+    fn ui_top(&self, ui: &mut egui::Ui, title: &str, wins: bool) {
+        ui.label(RichText::new(title).size(13.0));
+        let mut files: Vec<_> = self
+            .files
+            .iter()
+            .filter(|f| if wins { f.saved() > 0 } else { f.saved() <= 0 })
+            .collect();
+        files.sort_by_key(|f| if wins { -f.saved() } else { f.saved() });
+        if files.is_empty() {
+            ui.label(RichText::new("None").size(12.0).color(Color32::GRAY));
+        }
+        for file in files.into_iter().take(TOP_N) {
+            let name = file.path.file_name().unwrap_or_default().to_string_lossy();
+            let (sign, color) = if wins {
+                ("−", Color32::from_rgb(150, 220, 150))
+            } else {
+                ("+", Color32::from_rgb(240, 120, 120))
+            };
+            let mut text = format!("{sign}{} {name}", format_bytes(file.saved().unsigned_abs()));
+            if file.discarded {
+                text.push_str(" (original kept)");
+            }
+            ui.label(RichText::new(text).size(12.0).color(color))
+                .on_hover_text(file.path.display().to_string());
+        }
+    }
+
+    fn ui_histogram(&self, ui: &mut egui::Ui) {
+        let counts = self.histogram();
+        let max = counts.iter().copied().max().unwrap_or(0).max(1);
+
+        ui.label(RichText::new("Output size as a share of the source").size(13.0));
+        let (rect, _) =
+            ui.allocate_exact_size(Vec2::new(ui.available_width(), 80.0), Sense::hover());
+        let painter = ui.painter_at(rect);
+        let label_height = 14.0;
+        let slot = rect.width() / BUCKETS as f32;
+        let plot_height = rect.height() - 2.0 * label_height;
+        for (i, &count) in counts.iter().enumerate() {
+            let left = rect.left() + i as f32 * slot;
+            let height = plot_height * count as f32 / max as f32;
+            let bottom = rect.bottom() - label_height;
+            let bar = egui::Rect::from_min_max(
+                egui::pos2(left + 2.0, bottom - height),
+                egui::pos2(left + slot - 2.0, bottom),
+            );
+            let color = if i + 1 == BUCKETS {
+                Color32::from_rgb(240, 120, 120)
+            } else {
+                Color32::from_rgb(100, 160, 220)
+            };
+            painter.rect_filled(bar, 2.0, color);
+            let font = FontId::proportional(10.0);
+            if count > 0 {
+                let at = egui::pos2(bar.center().x, bar.top() - 1.0);
+                painter.text(
+                    at,
+                    Align2::CENTER_BOTTOM,
+                    count,
+                    font.clone(),
+                    Color32::GRAY,
+                );
+            }
+            let label = if i + 1 == BUCKETS {
+                "100%+".to_string()
+            } else {
+                format!("{}%", i * 10)
+            };
+            let at = egui::pos2(left + slot / 2.0, rect.bottom());
+            painter.text(at, Align2::CENTER_BOTTOM, label, font, Color32::GRAY);
+        }
+    }
+}
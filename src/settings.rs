@@ -0,0 +1,168 @@
+//! The app's persisted state, stored as versioned TOML.
+
+use crate::data_dir;
+use crate::log_file::LogFormat;
+use anyhow::{Context, Result, anyhow};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use toml::{Table, Value};
+use webp_converter_app::{ConvertOptions, Preset};
+
+/// Bumped whenever a field changes meaning; older versions are migrated in
+/// [`Settings::parse`]. Version 0 is the `key=value` format, handled by
+/// `MyApp::load_legacy`.
+pub const SETTINGS_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub version: u32,
+    pub input_path: String,
+    pub output_path: String,
+    pub options: ConvertOptions,
+    pub jobs_concurrent: bool,
+    pub log: LogSettings,
+    pub presets: Vec<Preset>,
+    pub jobs: Vec<JobSettings>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            version: SETTINGS_VERSION,
+            input_path: String::new(),
+            output_path: String::new(),
+            options: ConvertOptions::default(),
+            jobs_concurrent: false,
+            log: LogSettings::default(),
+            presets: Vec::new(),
+            jobs: Vec::new(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct LogSettings {
+    pub show_converted: bool,
+    pub show_skipped: bool,
+    pub show_errors: bool,
+    pub to_file: bool,
+    pub dir: String,
+    pub format: LogFormat,
+    pub keep: usize,
+}
+
+impl Default for LogSettings {
+    fn default() -> Self {
+        Self {
+            show_converted: true,
+            show_skipped: true,
+            show_errors: true,
+            to_file: false,
+            dir: data_dir()
+                .map(|d| d.join("logs").display().to_string())
+                .unwrap_or_default(),
+            format: LogFormat::Text,
+            keep: 30,
+        }
+    }
+}
+
+/// A queued job that had not finished when the app was closed.
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct JobSettings {
+    pub held: bool,
+    pub input: String,
+    pub output: String,
+    pub files: Vec<PathBuf>,
+    pub options: ConvertOptions,
+}
+
+/// A stored key this version does not read, kept so that saving does not
+/// drop what a newer version wrote. Keys inside arrays are not kept.
+pub struct Unknown {
+    path: Vec<String>,
+    value: Value,
+}
+
+impl Settings {
+    /// Reads settings saved by [`Settings::to_storage`], along with any keys
+    /// this version does not know. Returns `None` for the legacy format, and
+    /// an error for settings from a version whose fields mean something else.
+    pub fn parse(raw: &str) -> Option<Result<(Self, Vec<Unknown>)>> {
+        let table: Table = raw.parse().ok()?;
+        let version = table.get("version")?;
+        if let Some(version) = version.as_integer()
+            && version > i64::from(SETTINGS_VERSION)
+        {
+            return Some(Err(anyhow!("Settings are from a newer version ({version})")));
+        }
+        let mut ignored = Vec::new();
+        let settings = serde_ignored::deserialize(Value::Table(table.clone()), |path| {
+            ignored.extend(table_path(&path));
+        });
+        let unknown = ignored
+            .into_iter()
+            .filter_map(|path| {
+                let value = lookup(&table, &path)?.clone();
+                Some(Unknown { path, value })
+            })
+            .collect();
+        Some(settings.map(|s| (s, unknown)).context("Invalid settings"))
+    }
+
+    /// Serializes the settings, keeping `unknown` keys from a newer version.
+    pub fn to_storage(&self, unknown: &[Unknown]) -> Result<String> {
+        let mut table = Table::try_from(self).context("Failed to serialize settings")?;
+        for Unknown { path, value } in unknown {
+            insert_missing(&mut table, path, value);
+        }
+        toml::to_string(&table).context("Failed to serialize settings")
+    }
+}
+
+/// The keys leading to an ignored value, or `None` if it is inside an array.
+fn table_path(path: &serde_ignored::Path) -> Option<Vec<String>> {
+    use serde_ignored::Path;
+    match path {
+        Path::Root => Some(Vec::new()),
+        Path::Map { parent, key } => {
+            let mut keys = table_path(parent)?;
+            keys.push(key.clone());
+            Some(keys)
+        }
+        Path::Some { parent }
+        | Path::NewtypeStruct { parent }
+        | Path::NewtypeVariant { parent } => table_path(parent),
+        Path::Seq { .. } => None,
+    }
+}
+
+fn lookup<'a>(table: &'a Table, path: &[String]) -> Option<&'a Value> {
+    let (last, parents) = path.split_last()?;
+    let mut table = table;
+    for key in parents {
+        table = table.get(key)?.as_table()?;
+    }
+    table.get(last)
+}
+
+/// Inserts `value` at `path` unless something is already there.
+fn insert_missing(table: &mut Table, path: &[String], value: &Value) {
+    let Some((last, parents)) = path.split_last() else {
+        return;
+    };
+    let mut table = table;
+    for key in parents {
+        let entry = table
+            .entry(key.clone())
+            .or_insert_with(|| Value::Table(Table::new()));
+        let Some(inner) = entry.as_table_mut() else {
+            return;
+        };
+        table = inner;
+    }
+    table.entry(last.clone()).or_insert_with(|| value.clone());
+}
@@ -0,0 +1,131 @@
+use crate::filter::Probe;
+use crate::{ConvertOptions, LogEvent, SkipReason, Tally, is_supported_image};
+use anyhow::{Context, ensure};
+use notify::{EventKind, RecursiveMode, Watcher};
+use rayon::prelude::*;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+/// How long a file must stay unchanged before it is considered fully written.
+const SETTLE_TIME: Duration = Duration::from_millis(1500);
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// A file seen by the watcher that has not settled yet.
+struct PendingFile {
+    last_change: Instant,
+    len: Option<u64>,
+}
+
+/// Watches `input_dir` and converts images as they are created or modified,
+/// until `stop` is set.
+///
+/// Files are only picked up once their size has stopped changing for
+/// [`SETTLE_TIME`], so half-copied exports are not converted. Unlike the batch
+/// converters, an existing output is overwritten because the source changed.
+pub fn watch_dir_threads<F>(
+    input_dir: &str,
+    output_dir: &str,
+    options: &ConvertOptions,
+    stop: &AtomicBool,
+    log_fn: F,
+) -> anyhow::Result<()>
+where
+    F: Fn(LogEvent) + Sync + Send,
+{
+    ensure!(!input_dir.is_empty(), "Input path is empty.");
+    ensure!(
+        Path::new(input_dir).is_dir(),
+        "Input path is not a directory."
+    );
+
+    let filter = options.filter.compile()?;
+    options.prepare_output_dir(output_dir)?;
+    let output_dir = Path::new(output_dir);
+
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(tx)?;
+    watcher
+        .watch(Path::new(input_dir), RecursiveMode::NonRecursive)
+        .with_context(|| format!("Failed to watch {input_dir}"))?;
+
+    log_fn(LogEvent::Watching {
+        input_dir: input_dir.to_string(),
+    });
+
+    let tally = Tally::new(options);
+    let pool = options.thread_pool()?;
+    let mut seen = 0;
+    let mut pending: HashMap<PathBuf, PendingFile> = HashMap::new();
+
+    while !stop.load(Ordering::Relaxed) {
+        match rx.recv_timeout(POLL_INTERVAL) {
+            Ok(Ok(event)) => {
+                if matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+                    for path in event.paths.into_iter().filter(|p| is_supported_image(p)) {
+                        pending.insert(
+                            path,
+                            PendingFile {
+                                last_change: Instant::now(),
+                                len: None,
+                            },
+                        );
+                    }
+                }
+            }
+            Ok(Err(e)) => log_fn(LogEvent::Error {
+                path: None,
+                msg: format!("Watcher: {e}"),
+            }),
+            Err(mpsc::RecvTimeoutError::Timeout) => {}
+            Err(mpsc::RecvTimeoutError::Disconnected) => break,
+        }
+
+        let ready = take_settled(&mut pending);
+        seen += ready.len();
+        pool.install(|| {
+            ready.par_iter().for_each(|path| {
+                options.control.checkpoint(&log_fn);
+                log_fn(LogEvent::Detected { path: path.clone() });
+                let relative = path.strip_prefix(input_dir).unwrap_or(path);
+                let probe = Probe::new(path);
+                if let Some(rule) = filter.rejects(&probe, relative) {
+                    tally.skip(path, SkipReason::Filtered { rule }, &log_fn);
+                    return;
+                }
+                let output_path = options.output_path(path, output_dir);
+                tally.convert(&probe, &output_path, options, &log_fn);
+            });
+        });
+    }
+
+    log_fn(tally.finished(seen));
+
+    Ok(())
+}
+
+/// Removes and returns the files whose size has been stable for
+/// [`SETTLE_TIME`]. Files that vanished are dropped silently.
+fn take_settled(pending: &mut HashMap<PathBuf, PendingFile>) -> Vec<PathBuf> {
+    let now = Instant::now();
+    let mut ready = Vec::new();
+    pending.retain(|path, file| {
+        let Ok(meta) = fs::metadata(path) else {
+            return false;
+        };
+        if file.len != Some(meta.len()) {
+            file.len = Some(meta.len());
+            file.last_change = now;
+            return true;
+        }
+        if now.duration_since(file.last_change) < SETTLE_TIME {
+            return true;
+        }
+        ready.push(path.clone());
+        false
+    });
+    ready
+}